@@ -49,3 +49,33 @@ Selector labels
 app.kubernetes.io/name: {{ include "authentik-operator.name" . }}
 app.kubernetes.io/instance: {{ .Release.Name }}
 {{- end }}
+
+{{/*
+Rules for the namespaced resources managed by the operator
+*/}}
+{{- define "authentik-operator.namespacedRules" -}}
+- apiGroups: [""]
+  resources: ["secrets", "serviceaccounts", "services"]
+  verbs: ["*"]
+- apiGroups: [""]
+  resources: ["configmaps"]
+  verbs: ["get", "list", "watch"]
+- apiGroups: ["events.k8s.io"]
+  resources: ["events"]
+  verbs: ["create"]
+- apiGroups: ["apps"]
+  resources: ["deployments"]
+  verbs: ["*"]
+- apiGroups: ["autoscaling"]
+  resources: ["horizontalpodautoscalers"]
+  verbs: ["*"]
+- apiGroups: ["policy"]
+  resources: ["poddisruptionbudgets"]
+  verbs: ["*"]
+- apiGroups: ["networking.k8s.io"]
+  resources: ["ingresses"]
+  verbs: ["*"]
+- apiGroups: ["ak.dany.dev"]
+  resources: ["*"]
+  verbs: ["*"]
+{{- end }}
@@ -1,43 +1,271 @@
+use std::sync::Arc;
+
 use anyhow::{anyhow, Result};
-use k8s_openapi::api::core::v1::Secret;
-use kube::{Api, Client};
+use k8s_openapi::{
+    api::core::v1::{ConfigMap, Secret},
+    apimachinery::pkg::apis::meta::v1::ObjectMeta,
+};
+use kube::{Api, Client, ResourceExt};
+use sha2::{Digest, Sha256};
+
+use crate::{
+    metrics,
+    resources::{
+        authentik::crd::{
+            Authentik, AuthentikApiScheme, AuthentikCaBundle, AuthentikClientCertificate,
+        },
+        authentik_connection::crd::AuthentikConnection,
+    },
+};
 
 use super::{
+    registry,
     user::{GetSelf, GetSelfError},
-    AkApiRoute, AkClient,
+    AkApiRoute, AkClient, AkClientTls,
 };
 
-pub static TEMP_AUTH_TOKEN: &str = "AUTHENTIK_TEMP_AUTH_TOKEN";
+pub static BOOTSTRAP_TOKEN_IDENTIFIER: &str = "authentik-bootstrap-token";
+
+/// The tokens the operator uses to authenticate with an instance it manages.
+#[derive(Clone, Copy, Debug)]
+pub enum TokenKind {
+    /// The token the operator creates for itself.
+    Operator,
+    /// The token injected into the server, only used until the operator has its own.
+    Bootstrap,
+}
+
+impl TokenKind {
+    fn secret_name(self, instance: &str) -> String {
+        match self {
+            TokenKind::Operator => format!("ak-{}-api-operatortoken", instance),
+            TokenKind::Bootstrap => format!("ak-{}-bootstrap", instance),
+        }
+    }
+
+    fn registry_key(self, ns: &str, instance: &str) -> String {
+        let kind = match self {
+            TokenKind::Operator => "operator",
+            TokenKind::Bootstrap => "bootstrap",
+        };
+
+        format!("instance/{}/{}/{}", ns, instance, kind)
+    }
+}
 
-pub async fn get_valid_token(client: Client, ns: &str, instance: &str) -> Result<String> {
-    // Try a token in the secret first.
-    if let Some(secret) = get_valid_secret_token(client, ns, instance).await? {
-        return Ok(secret);
+/// Get a client for the instance referenced by a resource.
+/// This is either an `Authentik` resource, or an `AuthentikConnection` to an external instance.
+pub async fn get_client(client: Client, ns: &str, instance: &str) -> Result<Arc<AkClient>> {
+    let servers: Api<Authentik> = Api::namespaced(client.clone(), ns);
+    if let Some(server) = servers.get_opt(instance).await? {
+        return get_instance_client(client, &server).await;
     }
 
-    // Check if the temporally token is still valid.
-    let ak = AkClient::new(TEMP_AUTH_TOKEN, instance, ns)?;
-    if validate_token(&ak).await? {
-        return Ok(TEMP_AUTH_TOKEN.to_string());
+    let connections: Api<AuthentikConnection> = Api::namespaced(client.clone(), ns);
+    let connection = connections.get_opt(instance).await?.ok_or(anyhow!(
+        "No Authentik instance or connection named `{}` was found.",
+        instance
+    ))?;
+
+    // The client is built again once it expires, or the spec or any referenced secret changes.
+    let mut fingerprint = Sha256::new();
+    fingerprint.update(serde_json::to_vec(&connection.spec)?);
+    let token_ref = &connection.spec.token_ref;
+    let api_key = get_secret_value(
+        client.clone(),
+        ns,
+        &token_ref.name,
+        &token_ref.key,
+        &mut fingerprint,
+    )
+    .await?
+    .ok_or(anyhow!("Missing token secret `{}`.", token_ref.name))?;
+    let tls = get_tls(
+        client,
+        ns,
+        connection.spec.ca.as_ref(),
+        connection.spec.client_certificate.as_ref(),
+        &mut fingerprint,
+    )
+    .await?;
+
+    let key = format!("connection/{}/{}", ns, instance);
+    let fingerprint = fingerprint.finalize().to_vec();
+    if let Some(Some(ak)) = registry::get(&key, &fingerprint) {
+        return Ok(ak);
+    }
+
+    // The token of a connection is managed by the user, so it's not validated here.
+    let ak = AkClient::new(&api_key, &connection.spec.url, &tls)?;
+    Ok(registry::insert(&key, fingerprint, ak))
+}
+
+/// Get a client for an instance managed by the operator, through its in-cluster service.
+/// The operator token is used if it's valid, otherwise the bootstrap token is tried.
+pub async fn get_instance_client(client: Client, obj: &Authentik) -> Result<Arc<AkClient>> {
+    if let Some(ak) = get_validated_client(client.clone(), obj, TokenKind::Operator).await? {
+        return Ok(ak);
+    }
+
+    if let Some(ak) = get_validated_client(client, obj, TokenKind::Bootstrap).await? {
+        return Ok(ak);
     }
 
     Err(anyhow!("No valid authentication token was found."))
 }
 
-pub async fn get_valid_secret_token(
+/// Get a client using the operator token, if it exists and is valid.
+pub async fn get_operator_client(client: Client, obj: &Authentik) -> Result<Option<Arc<AkClient>>> {
+    get_validated_client(client, obj, TokenKind::Operator).await
+}
+
+/// Check if the token is valid for a managed instance.
+/// The result is not cached, as the token is not stored yet.
+pub async fn is_valid_token(client: Client, obj: &Authentik, token: &str) -> Result<bool> {
+    let ns = obj
+        .namespace()
+        .ok_or(anyhow!("Missing namespace `{}`.", obj.name_any()))?;
+    let api = &obj.spec.api;
+    let tls = get_tls(
+        client,
+        &ns,
+        api.ca.as_ref(),
+        api.client_certificate.as_ref(),
+        &mut Sha256::new(),
+    )
+    .await?;
+
+    validate_token(&AkClient::new(token, &base_url(obj)?, &tls)?).await
+}
+
+/// Forget the client of the token, so the token is read and validated again on the next use.
+/// This must be done whenever the operator changes the token itself.
+pub fn invalidate(obj: &Authentik, kind: TokenKind) {
+    if let Some(ns) = obj.namespace() {
+        registry::invalidate(&kind.registry_key(&ns, &obj.name_any()));
+    }
+}
+
+/// Get a client for a managed instance with the token of the given kind, if the token is valid.
+/// The outcome of the validation is cached until it expires, or the spec or any referenced secret changes.
+async fn get_validated_client(
+    client: Client,
+    obj: &Authentik,
+    kind: TokenKind,
+) -> Result<Option<Arc<AkClient>>> {
+    let instance = obj.name_any();
+    let ns = obj
+        .namespace()
+        .ok_or(anyhow!("Missing namespace `{}`.", instance.clone()))?;
+    let api = &obj.spec.api;
+    let base_url = base_url(obj)?;
+
+    let mut fingerprint = Sha256::new();
+    fingerprint.update(serde_json::to_vec(api)?);
+    let secret_name = kind.secret_name(&instance);
+    let token = match get_secret_value(client.clone(), &ns, &secret_name, "token", &mut fingerprint)
+        .await?
+    {
+        Some(token) => token,
+        None => return Ok(None),
+    };
+    let tls = get_tls(
+        client,
+        &ns,
+        api.ca.as_ref(),
+        api.client_certificate.as_ref(),
+        &mut fingerprint,
+    )
+    .await?;
+
+    let key = kind.registry_key(&ns, &instance);
+    let fingerprint = fingerprint.finalize().to_vec();
+    if let Some(ak) = registry::get(&key, &fingerprint) {
+        return Ok(ak);
+    }
+
+    let ak = AkClient::new(&token, &base_url, &tls)?;
+    if !validate_token(&ak).await? {
+        metrics::token_rejected(&ns, &instance);
+        registry::reject(&key, fingerprint);
+        return Ok(None);
+    }
+
+    Ok(Some(registry::insert(&key, fingerprint, ak)))
+}
+
+/// The URL of the API of a managed instance, through its service.
+fn base_url(obj: &Authentik) -> Result<String> {
+    let api = &obj.spec.api;
+    let scheme = match api.scheme {
+        AuthentikApiScheme::Http => "http",
+        AuthentikApiScheme::Https => "https",
+    };
+    // Objects created before the pattern was enforced could glue the prefix onto the host.
+    if !api.path_prefix.is_empty() && !api.path_prefix.starts_with('/') {
+        return Err(anyhow!("api.pathPrefix must start with a `/`."));
+    }
+    let ns = obj
+        .namespace()
+        .ok_or(anyhow!("Missing namespace `{}`.", obj.name_any()))?;
+
+    Ok(format!(
+        "{}://authentik-{}.{}{}",
+        scheme,
+        obj.name_any(),
+        ns,
+        api.path_prefix
+    ))
+}
+
+/// Add the version of a referenced object to the fingerprint, so any change to it builds a new client.
+fn add_version(fingerprint: &mut Sha256, metadata: &ObjectMeta) {
+    let version = metadata.resource_version.as_deref().unwrap_or_default();
+    fingerprint.update((version.len() as u64).to_be_bytes());
+    fingerprint.update(version);
+}
+
+async fn get_tls(
     client: Client,
     ns: &str,
-    instance: &str,
-) -> Result<Option<String>> {
-    if let Some(secret) = get_token_secret(client, ns, instance).await? {
-        let ak = AkClient::new(&secret, instance, ns)?;
+    ca: Option<&AuthentikCaBundle>,
+    certificate: Option<&AuthentikClientCertificate>,
+    fingerprint: &mut Sha256,
+) -> Result<AkClientTls> {
+    let mut tls = AkClientTls::default();
 
-        if validate_token(&ak).await? {
-            return Ok(Some(secret));
-        }
+    if let Some(ca) = ca {
+        let bundle = match (&ca.secret_name, &ca.config_map_name) {
+            (Some(name), None) => {
+                get_secret_value(client.clone(), ns, name, &ca.key, fingerprint).await?
+            }
+            (None, Some(name)) => {
+                get_config_map_value(client.clone(), ns, name, &ca.key, fingerprint).await?
+            }
+            _ => {
+                return Err(anyhow!(
+                    "Exactly one of secretName and configMapName must be set for the CA bundle."
+                ))
+            }
+        };
+        tls.ca = Some(
+            bundle
+                .ok_or(anyhow!("The CA bundle does not exist."))?
+                .into_bytes(),
+        );
     }
 
-    Ok(None)
+    if let Some(certificate) = certificate {
+        let name = &certificate.secret_name;
+        let cert = get_secret_value(client.clone(), ns, name, "tls.crt", fingerprint).await?;
+        let key = get_secret_value(client, ns, name, "tls.key", fingerprint).await?;
+        let (cert, key) = cert
+            .zip(key)
+            .ok_or(anyhow!("Missing client certificate secret `{}`.", name))?;
+        tls.certificate = Some((cert.into_bytes(), key.into_bytes()));
+    }
+
+    Ok(tls)
 }
 
 async fn validate_token(ak: &AkClient) -> Result<bool> {
@@ -48,21 +276,55 @@ async fn validate_token(ak: &AkClient) -> Result<bool> {
     }
 }
 
-async fn get_token_secret(client: Client, ns: &str, instance: &str) -> Result<Option<String>> {
-    // Get the token from a secret, iff it exists.
+async fn get_secret_value(
+    client: Client,
+    ns: &str,
+    name: &str,
+    key: &str,
+    fingerprint: &mut Sha256,
+) -> Result<Option<String>> {
+    // Get the value from a secret, iff it exists.
     let api: Api<Secret> = Api::namespaced(client, ns);
-    let name = format!("ak-{}-api-operatortoken", instance);
 
-    if let Some(secret) = api.get_opt(&name).await? {
+    if let Some(secret) = api.get_opt(name).await? {
+        add_version(fingerprint, &secret.metadata);
         let mut data = secret
             .data
-            .ok_or(anyhow!("Token secret does not contain any data"))?;
-        let token = data
-            .remove("token")
-            .ok_or(anyhow!("Token secret does not contain a token."))?;
-        let token_string = String::from_utf8(token.0)?;
+            .ok_or(anyhow!("Secret `{}` does not contain any data", name))?;
+        let value = data.remove(key).ok_or(anyhow!(
+            "Secret `{}` does not contain the key `{}`.",
+            name,
+            key
+        ))?;
+
+        Ok(Some(String::from_utf8(value.0)?))
+    } else {
+        Ok(None)
+    }
+}
+
+async fn get_config_map_value(
+    client: Client,
+    ns: &str,
+    name: &str,
+    key: &str,
+    fingerprint: &mut Sha256,
+) -> Result<Option<String>> {
+    let api: Api<ConfigMap> = Api::namespaced(client, ns);
+
+    if let Some(config_map) = api.get_opt(name).await? {
+        add_version(fingerprint, &config_map.metadata);
+        let value = config_map
+            .data
+            .unwrap_or_default()
+            .remove(key)
+            .ok_or(anyhow!(
+                "Config map `{}` does not contain the key `{}`.",
+                name,
+                key
+            ))?;
 
-        Ok(Some(token_string))
+        Ok(Some(value))
     } else {
         Ok(None)
     }
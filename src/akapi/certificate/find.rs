@@ -1,9 +1,7 @@
 use async_trait::async_trait;
-use reqwest::StatusCode;
-use serde::Deserialize;
 use thiserror::Error;
 
-use crate::akapi::{types::Certificate, AkApiRoute, AkClient};
+use crate::akapi::{error::ApiError, types::Certificate, AkApiRoute, AkClient};
 
 pub struct FindCertificate;
 
@@ -15,7 +13,7 @@ impl AkApiRoute for FindCertificate {
 
     #[instrument]
     async fn send(ak: &AkClient, body: Self::Body) -> Result<Self::Response, Self::Error> {
-        let mut query = vec![("page_size", "1000".to_string())];
+        let mut query = vec![];
 
         if let Some(name) = body.name {
             query.push(("name", name));
@@ -25,23 +23,7 @@ impl AkApiRoute for FindCertificate {
             query.push(("has_key", has_keys.to_string()));
         }
 
-        let res = ak
-            .get("/api/v3/crypto/certificatekeypairs/")
-            .query(&query)
-            .send()
-            .await?;
-
-        match res.status() {
-            StatusCode::OK => {
-                let body: FindCertificateResponse = res.json().await?;
-
-                Ok(body.results)
-            }
-            code => Err(Self::Error::Unknown(format!(
-                "Invalid status code {}",
-                code
-            ))),
-        }
+        ak.list("/api/v3/crypto/certificatekeypairs/", &query).await
     }
 }
 
@@ -51,15 +33,10 @@ pub struct FindCertificateBody {
     pub has_keys: Option<bool>,
 }
 
-#[derive(Debug, Deserialize)]
-pub struct FindCertificateResponse {
-    pub results: Vec<Certificate>,
-}
-
 #[derive(Error, Debug)]
 pub enum FindCertificateError {
-    #[error("An unknown error occured ({0}).")]
-    Unknown(String),
+    #[error(transparent)]
+    ApiError(#[from] ApiError),
     #[error("Failed to send HTTP request: {0}")]
     ConnectionError(#[from] reqwest::Error),
 }
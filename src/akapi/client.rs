@@ -1,48 +1,88 @@
-use std::time::Duration;
+use std::{sync::Arc, time::Duration};
 
 use anyhow::Result;
 use reqwest::{
     header::{HeaderMap, AUTHORIZATION},
-    RequestBuilder,
+    Certificate, Identity, Method,
 };
+use tokio::sync::Semaphore;
+
+use super::request::{self, AkRequest};
+
+/// TLS settings used when connecting to the API, all PEM encoded.
+#[derive(Debug, Default)]
+pub struct AkClientTls {
+    pub ca: Option<Vec<u8>>,
+    pub certificate: Option<(Vec<u8>, Vec<u8>)>,
+}
 
 #[derive(Debug)]
 pub struct AkClient {
     client: reqwest::Client,
-    host: String,
+    base_url: String,
+    // Limit of concurrent requests, shared with the other clients of the instance.
+    limit: Arc<Semaphore>,
+    // Key of the client within the registry, if it's stored there.
+    pub(super) registry_key: Option<String>,
 }
 
 impl AkClient {
-    pub fn new(api_key: &str, instance: &str, namespace: &str) -> Result<Self> {
+    /// Create a client for an instance reachable at the given base URL.
+    /// The CA bundle, if given, is trusted in addition to the system roots.
+    pub fn new(api_key: &str, base_url: &str, tls: &AkClientTls) -> Result<Self> {
         let user_agent = format!("{}/{}", env!("CARGO_PKG_NAME"), env!("CARGO_PKG_VERSION"));
         let mut headers = HeaderMap::new();
         headers.insert(AUTHORIZATION, format!("Bearer {}", api_key).parse()?);
 
-        let client = reqwest::Client::builder()
+        let mut builder = reqwest::Client::builder()
             .default_headers(headers)
             .user_agent(user_agent)
-            .timeout(Duration::from_secs(120))
-            .build()?;
+            .timeout(Duration::from_secs(120));
+        if let Some(ca) = &tls.ca {
+            for cert in Certificate::from_pem_bundle(ca)? {
+                builder = builder.add_root_certificate(cert);
+            }
+        }
+        if let Some((cert, key)) = &tls.certificate {
+            builder = builder.identity(Identity::from_pkcs8_pem(cert, key)?);
+        }
 
+        let base_url = base_url.trim_end_matches('/').to_string();
         Ok(Self {
-            client,
-            host: format!("authentik-{}.{}", instance, namespace),
+            client: builder.build()?,
+            limit: request::limit(&base_url),
+            base_url,
+            registry_key: None,
         })
     }
 
-    pub fn get(&self, path: &str) -> RequestBuilder {
-        self.client.get(format!("http://{}{}", self.host, path))
+    fn request(&self, method: Method, path: &str) -> AkRequest {
+        let builder = self
+            .client
+            .request(method.clone(), format!("{}{}", self.base_url, path));
+
+        AkRequest::new(
+            builder,
+            &method,
+            self.limit.clone(),
+            path,
+            self.registry_key.clone(),
+        )
+    }
+
+    pub fn get(&self, path: &str) -> AkRequest {
+        self.request(Method::GET, path)
     }
 
-    pub fn patch(&self, path: &str) -> RequestBuilder {
-        self.client.patch(format!("http://{}{}", self.host, path))
+    pub fn patch(&self, path: &str) -> AkRequest {
+        self.request(Method::PATCH, path)
     }
 
-    pub fn post(&self, path: &str) -> RequestBuilder {
-        self.client.post(format!("http://{}{}", self.host, path))
+    pub fn post(&self, path: &str) -> AkRequest {
+        self.request(Method::POST, path)
     }
 
-    pub fn delete(&self, path: &str) -> RequestBuilder {
-        self.client.delete(format!("http://{}{}", self.host, path))
+    pub fn delete(&self, path: &str) -> AkRequest {
+        self.request(Method::DELETE, path)
     }
 }
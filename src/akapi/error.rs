@@ -0,0 +1,146 @@
+use std::{collections::BTreeMap, fmt};
+
+use reqwest::{Response, StatusCode};
+use serde_json::Value;
+use thiserror::Error;
+
+/// An unexpected response from the Authentik API.
+#[derive(Error, Debug)]
+pub struct ApiError {
+    pub status: StatusCode,
+    pub path: String,
+    // Validation errors returned by Authentik, by field.
+    pub errors: BTreeMap<String, Vec<String>>,
+    // The raw body, only kept if it could not be parsed.
+    pub body: Option<String>,
+}
+
+impl ApiError {
+    pub async fn from_response(res: Response) -> Self {
+        let status = res.status();
+        let path = res.url().path().to_string();
+        let body = res.text().await.unwrap_or_default();
+
+        match serde_json::from_str::<BTreeMap<String, Value>>(&body) {
+            Ok(fields) => Self {
+                status,
+                path,
+                errors: fields
+                    .into_iter()
+                    .map(|(field, value)| (field, messages(value)))
+                    .collect(),
+                body: None,
+            },
+            Err(_) => Self {
+                status,
+                path,
+                errors: BTreeMap::new(),
+                body: Some(body).filter(|body| !body.is_empty()),
+            },
+        }
+    }
+
+    /// Check if the request failed because the object already exists.
+    pub fn is_unique_violation(&self) -> bool {
+        self.status == StatusCode::BAD_REQUEST
+            && self
+                .errors
+                .values()
+                .flatten()
+                .any(|message| message.contains("already exists"))
+    }
+}
+
+impl fmt::Display for ApiError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Authentik returned {} for {}", self.status, self.path)?;
+
+        if !self.errors.is_empty() {
+            let errors: Vec<String> = self
+                .errors
+                .iter()
+                .map(|(field, messages)| format!("{}: {}", field, messages.join(" ")))
+                .collect();
+            write!(f, " ({})", errors.join("; "))?;
+        } else if let Some(body) = &self.body {
+            // Keep the message readable, the body could be a full error page.
+            write!(f, " ({})", body.chars().take(200).collect::<String>())?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Flatten the messages of a single field, which can be nested for lists and objects.
+fn messages(value: Value) -> Vec<String> {
+    match value {
+        Value::String(message) => vec![message],
+        Value::Array(values) => values.into_iter().flat_map(messages).collect(),
+        Value::Object(fields) => fields
+            .into_iter()
+            .flat_map(|(field, value)| {
+                messages(value)
+                    .into_iter()
+                    .map(move |message| format!("{}: {}", field, message))
+            })
+            .collect(),
+        Value::Null => vec![],
+        value => vec![value.to_string()],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use reqwest::{Response, StatusCode};
+
+    use super::ApiError;
+
+    fn response(status: u16, body: &str) -> Response {
+        http::Response::builder()
+            .status(status)
+            .body(body.to_string())
+            .unwrap()
+            .into()
+    }
+
+    #[tokio::test]
+    async fn parses_validation_errors() {
+        let body = r#"{"name": ["Group with this name already exists."], "attributes": {"size": "Too large."}, "detail": null}"#;
+        let error = ApiError::from_response(response(400, body)).await;
+
+        assert_eq!(error.status, StatusCode::BAD_REQUEST);
+        assert_eq!(
+            error.errors["name"],
+            vec!["Group with this name already exists."]
+        );
+        assert_eq!(error.errors["attributes"], vec!["size: Too large."]);
+        assert!(error.errors["detail"].is_empty());
+        assert!(error.body.is_none());
+        assert!(error.is_unique_violation());
+    }
+
+    #[tokio::test]
+    async fn keeps_a_body_which_is_not_json() {
+        let error = ApiError::from_response(response(502, "Bad Gateway")).await;
+
+        assert_eq!(error.status, StatusCode::BAD_GATEWAY);
+        assert!(error.errors.is_empty());
+        assert_eq!(error.body.as_deref(), Some("Bad Gateway"));
+        assert!(!error.is_unique_violation());
+    }
+
+    #[tokio::test]
+    async fn drops_an_empty_body() {
+        let error = ApiError::from_response(response(404, "")).await;
+
+        assert!(error.body.is_none());
+        assert!(!error.to_string().contains('('));
+    }
+
+    #[tokio::test]
+    async fn truncates_a_long_body() {
+        let error = ApiError::from_response(response(500, &"x".repeat(1000))).await;
+
+        assert!(error.to_string().len() < 300);
+    }
+}
@@ -2,7 +2,7 @@ use async_trait::async_trait;
 use reqwest::StatusCode;
 use thiserror::Error;
 
-use crate::akapi::{AkApiRoute, AkClient};
+use crate::akapi::{error::ApiError, AkApiRoute, AkClient};
 
 pub struct DeleteFlow;
 
@@ -22,10 +22,7 @@ impl AkApiRoute for DeleteFlow {
         match res.status() {
             StatusCode::NO_CONTENT => Ok(()),
             StatusCode::NOT_FOUND => Err(Self::Error::NotFound),
-            code => Err(Self::Error::Unknown(format!(
-                "Invalid status code {}",
-                code
-            ))),
+            _ => Err(ApiError::from_response(res).await.into()),
         }
     }
 }
@@ -34,8 +31,8 @@ impl AkApiRoute for DeleteFlow {
 pub enum DeleteFlowError {
     #[error("The given flow was not found.")]
     NotFound,
-    #[error("An unknown error occured ({0}).")]
-    Unknown(String),
+    #[error(transparent)]
+    ApiError(#[from] ApiError),
     #[error("Failed to send HTTP request: {0}")]
     ConnectionError(#[from] reqwest::Error),
 }
@@ -2,7 +2,7 @@ use async_trait::async_trait;
 use reqwest::StatusCode;
 use thiserror::Error;
 
-use crate::akapi::{types::Flow, AkApiRoute, AkClient};
+use crate::akapi::{error::ApiError, types::Flow, AkApiRoute, AkClient};
 
 pub struct GetFlow;
 
@@ -25,18 +25,15 @@ impl AkApiRoute for GetFlow {
 
                 Ok(flow)
             }
-            code => Err(Self::Error::Unknown(format!(
-                "Invalid status code {}",
-                code
-            ))),
+            _ => Err(ApiError::from_response(res).await.into()),
         }
     }
 }
 
 #[derive(Error, Debug)]
 pub enum GetFlowError {
-    #[error("An unknown error occured ({0}).")]
-    Unknown(String),
+    #[error(transparent)]
+    ApiError(#[from] ApiError),
     #[error("Failed to send HTTP request: {0}")]
     ConnectionError(#[from] reqwest::Error),
 }
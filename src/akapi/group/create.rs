@@ -3,7 +3,7 @@ use reqwest::StatusCode;
 use serde::Serialize;
 use thiserror::Error;
 
-use crate::akapi::{types::Group, AkApiRoute, AkClient};
+use crate::akapi::{error::ApiError, types::Group, AkApiRoute, AkClient};
 
 pub struct CreateGroup;
 
@@ -23,10 +23,15 @@ impl AkApiRoute for CreateGroup {
 
                 Ok(body)
             }
-            code => Err(Self::Error::Unknown(format!(
-                "Invalid status code {}",
-                code
-            ))),
+            StatusCode::BAD_REQUEST => {
+                let error = ApiError::from_response(res).await;
+                if error.is_unique_violation() {
+                    Err(Self::Error::ExistsError)
+                } else {
+                    Err(error.into())
+                }
+            }
+            _ => Err(ApiError::from_response(res).await.into()),
         }
     }
 }
@@ -43,8 +48,8 @@ pub struct CreateGroupBody {
 pub enum CreateGroupError {
     #[error("The group probably already exists!")]
     ExistsError,
-    #[error("An unknown error occured ({0}).")]
-    Unknown(String),
+    #[error(transparent)]
+    ApiError(#[from] ApiError),
     #[error("Failed to send HTTP request: {0}")]
     ConnectionError(#[from] reqwest::Error),
 }
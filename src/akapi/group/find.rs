@@ -1,9 +1,7 @@
 use async_trait::async_trait;
-use reqwest::StatusCode;
-use serde::Deserialize;
 use thiserror::Error;
 
-use crate::akapi::{types::Group, AkApiRoute, AkClient};
+use crate::akapi::{error::ApiError, types::Group, AkApiRoute, AkClient};
 
 pub struct FindGroup;
 
@@ -15,25 +13,13 @@ impl AkApiRoute for FindGroup {
 
     #[instrument]
     async fn send(ak: &AkClient, body: Self::Body) -> Result<Self::Response, Self::Error> {
-        let mut query = vec![("page_size", "1000".to_string())];
+        let mut query = vec![];
 
         if let Some(name) = body.name {
             query.push(("name", name));
         }
 
-        let res = ak.get("/api/v3/core/groups/").query(&query).send().await?;
-
-        match res.status() {
-            StatusCode::OK => {
-                let body: FindGroupResponse = res.json().await?;
-
-                Ok(body.results)
-            }
-            code => Err(Self::Error::Unknown(format!(
-                "Invalid status code {}",
-                code
-            ))),
-        }
+        ak.list("/api/v3/core/groups/", &query).await
     }
 }
 
@@ -42,15 +28,10 @@ pub struct FindGroupBody {
     pub name: Option<String>,
 }
 
-#[derive(Debug, Deserialize)]
-pub struct FindGroupResponse {
-    pub results: Vec<Group>,
-}
-
 #[derive(Error, Debug)]
 pub enum FindGroupError {
-    #[error("An unknown error occured ({0}).")]
-    Unknown(String),
+    #[error(transparent)]
+    ApiError(#[from] ApiError),
     #[error("Failed to send HTTP request: {0}")]
     ConnectionError(#[from] reqwest::Error),
 }
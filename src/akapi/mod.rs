@@ -13,9 +13,14 @@ pub mod user;
 
 pub mod auth;
 mod client;
+pub mod error;
+mod pagination;
+mod registry;
+mod request;
 pub mod types;
 
-pub use client::AkClient;
+pub use client::{AkClient, AkClientTls};
+pub use request::AkRequest;
 
 pub static API_USER: &str = "ak-operator";
 
@@ -31,7 +36,7 @@ pub fn token_identifier_name(instance: &str, purpose: &str) -> String {
 pub trait AkApiRoute {
     type Body;
     type Response;
-    type Error: From<reqwest::Error>;
+    type Error: From<reqwest::Error> + From<error::ApiError>;
 
     async fn send(ak: &AkClient, body: Self::Body) -> Result<Self::Response, Self::Error>;
 }
@@ -0,0 +1,85 @@
+use reqwest::StatusCode;
+use serde::{de::DeserializeOwned, Deserialize};
+
+use super::{error::ApiError, AkClient};
+
+// Amount of objects requested per page.
+static PAGE_SIZE: usize = 100;
+
+#[derive(Debug, Deserialize)]
+pub struct Page<T> {
+    pub pagination: Pagination,
+    pub results: Vec<T>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Pagination {
+    // The number of the next page, or 0 if this is the last one.
+    pub next: usize,
+}
+
+impl AkClient {
+    /// Request all pages of a list route, following the pagination of the responses.
+    pub async fn list<T, E>(&self, path: &str, query: &[(&str, String)]) -> Result<Vec<T>, E>
+    where
+        T: DeserializeOwned,
+        E: From<ApiError> + From<reqwest::Error>,
+    {
+        let mut results = vec![];
+        let mut page = 1;
+
+        loop {
+            let res = self
+                .get(path)
+                .query(query)
+                .query(&[("page", page), ("page_size", PAGE_SIZE)])
+                .send()
+                .await?;
+            if res.status() != StatusCode::OK {
+                return Err(ApiError::from_response(res).await.into());
+            }
+
+            let body: Page<T> = res.json().await?;
+            results.extend(body.results);
+
+            page = match next_page(page, body.pagination.next) {
+                Some(next) => next,
+                None => break,
+            };
+        }
+
+        Ok(results)
+    }
+}
+
+/// The page to request after the current one, if any.
+/// Also stops when the page would be repeated, to never loop forever.
+fn next_page(page: usize, next: usize) -> Option<usize> {
+    match next {
+        0 => None,
+        next if next <= page => None,
+        next => Some(next),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::next_page;
+
+    #[test]
+    fn follows_the_next_page() {
+        assert_eq!(next_page(1, 2), Some(2));
+        assert_eq!(next_page(2, 5), Some(5));
+    }
+
+    #[test]
+    fn stops_on_the_last_page() {
+        assert_eq!(next_page(1, 0), None);
+    }
+
+    #[test]
+    fn stops_when_a_page_would_repeat() {
+        assert_eq!(next_page(3, 3), None);
+        assert_eq!(next_page(3, 1), None);
+    }
+}
@@ -2,7 +2,7 @@ use async_trait::async_trait;
 use reqwest::StatusCode;
 use thiserror::Error;
 
-use crate::akapi::{types::OAuthProvider, AkApiRoute, AkClient};
+use crate::akapi::{error::ApiError, types::OAuthProvider, AkApiRoute, AkClient};
 
 pub struct CreateOAuthProvider;
 
@@ -26,18 +26,15 @@ impl AkApiRoute for CreateOAuthProvider {
 
                 Ok(body)
             }
-            code => Err(Self::Error::Unknown(format!(
-                "Invalid status code {}",
-                code
-            ))),
+            _ => Err(ApiError::from_response(res).await.into()),
         }
     }
 }
 
 #[derive(Error, Debug)]
 pub enum CreateOAuthProviderError {
-    #[error("An unknown error occured ({0}).")]
-    Unknown(String),
+    #[error(transparent)]
+    ApiError(#[from] ApiError),
     #[error("Failed to send HTTP request: {0}")]
     ConnectionError(#[from] reqwest::Error),
 }
@@ -1,9 +1,7 @@
 use async_trait::async_trait;
-use reqwest::StatusCode;
-use serde::Deserialize;
 use thiserror::Error;
 
-use crate::akapi::{types::OAuthProvider, AkApiRoute, AkClient};
+use crate::akapi::{error::ApiError, types::OAuthProvider, AkApiRoute, AkClient};
 
 pub struct FindOAuthProvider;
 
@@ -15,29 +13,13 @@ impl AkApiRoute for FindOAuthProvider {
 
     #[instrument]
     async fn send(ak: &AkClient, body: Self::Body) -> Result<Self::Response, Self::Error> {
-        let mut query = vec![("page_size", "1000".to_string())];
+        let mut query = vec![];
 
         if let Some(name) = body.name {
             query.push(("name", name));
         }
 
-        let res = ak
-            .get("/api/v3/providers/oauth2/")
-            .query(&query)
-            .send()
-            .await?;
-
-        match res.status() {
-            StatusCode::OK => {
-                let body: FindOAuthProviderResponse = res.json().await?;
-
-                Ok(body.results)
-            }
-            code => Err(Self::Error::Unknown(format!(
-                "Invalid status code {}",
-                code
-            ))),
-        }
+        ak.list("/api/v3/providers/oauth2/", &query).await
     }
 }
 
@@ -46,15 +28,10 @@ pub struct FindOAuthProviderBody {
     pub name: Option<String>,
 }
 
-#[derive(Debug, Deserialize)]
-pub struct FindOAuthProviderResponse {
-    pub results: Vec<OAuthProvider>,
-}
-
 #[derive(Error, Debug)]
 pub enum FindOAuthProviderError {
-    #[error("An unknown error occured ({0}).")]
-    Unknown(String),
+    #[error(transparent)]
+    ApiError(#[from] ApiError),
     #[error("Failed to send HTTP request: {0}")]
     ConnectionError(#[from] reqwest::Error),
 }
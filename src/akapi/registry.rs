@@ -0,0 +1,74 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use lazy_static::lazy_static;
+
+use super::AkClient;
+
+// How long the outcome of a token validation is trusted, before it's validated again.
+static TOKEN_TTL: Duration = Duration::from_secs(5 * 60);
+
+struct Entry {
+    // Hash over the settings the client was built with.
+    fingerprint: Vec<u8>,
+    // The client, or none if the token was rejected.
+    client: Option<Arc<AkClient>>,
+    validated: Instant,
+}
+
+lazy_static! {
+    static ref CLIENTS: Mutex<HashMap<String, Entry>> = Mutex::new(HashMap::new());
+}
+
+/// Get the outcome stored for the key, if it was built with the same settings and hasn't expired.
+/// This is either a client with a valid token, or none if the token was rejected.
+pub fn get(key: &str, fingerprint: &[u8]) -> Option<Option<Arc<AkClient>>> {
+    let clients = CLIENTS.lock().expect("Client registry is poisoned");
+    let entry = clients.get(key)?;
+
+    if entry.fingerprint != fingerprint || entry.validated.elapsed() > TOKEN_TTL {
+        return None;
+    }
+
+    Some(entry.client.clone())
+}
+
+/// Store a client with a validated token, replacing the previous client for the key.
+pub fn insert(key: &str, fingerprint: Vec<u8>, mut client: AkClient) -> Arc<AkClient> {
+    client.registry_key = Some(key.to_string());
+    let client = Arc::new(client);
+
+    store(key, fingerprint, Some(client.clone()));
+    client
+}
+
+/// Remember that the token for the key was rejected, so it's not validated again until it expires.
+pub fn reject(key: &str, fingerprint: Vec<u8>) {
+    store(key, fingerprint, None);
+}
+
+/// Drop the client for the key, so the token is validated again on the next use.
+pub fn invalidate(key: &str) {
+    CLIENTS
+        .lock()
+        .expect("Client registry is poisoned")
+        .remove(key);
+}
+
+fn store(key: &str, fingerprint: Vec<u8>, client: Option<Arc<AkClient>>) {
+    let mut clients = CLIENTS.lock().expect("Client registry is poisoned");
+
+    // Expired entries are never used again, e.g. the ones of deleted instances.
+    clients.retain(|_, entry| entry.validated.elapsed() <= TOKEN_TTL);
+    clients.insert(
+        key.to_string(),
+        Entry {
+            fingerprint,
+            client,
+            validated: Instant::now(),
+        },
+    );
+}
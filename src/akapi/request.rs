@@ -0,0 +1,371 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex, Weak},
+    time::{Duration, Instant},
+};
+
+use lazy_static::lazy_static;
+use rand::{thread_rng, Rng};
+use reqwest::{header::RETRY_AFTER, Method, RequestBuilder, Response, StatusCode};
+use serde::Serialize;
+use tokio::sync::Semaphore;
+
+use crate::metrics;
+
+use super::registry;
+
+// Maximum amount of requests running at the same time, per Authentik instance.
+static MAX_CONCURRENT_REQUESTS: usize = 10;
+// Maximum amount of attempts for a single idempotent request.
+static MAX_ATTEMPTS: u32 = 5;
+static BACKOFF_BASE: Duration = Duration::from_millis(500);
+static BACKOFF_MAX: Duration = Duration::from_secs(30);
+// Maximum time spent waiting between attempts, so a reconcilidation isn't blocked for long.
+static RETRY_MAX: Duration = Duration::from_secs(60);
+
+lazy_static! {
+    // Clients of the same instance share a limit, which is dropped together with the last client.
+    static ref LIMITS: Mutex<HashMap<String, Weak<Semaphore>>> = Mutex::new(HashMap::new());
+}
+
+/// Get the limit of concurrent requests for the instance at the base URL.
+pub(super) fn limit(base_url: &str) -> Arc<Semaphore> {
+    let mut limits = LIMITS.lock().expect("Request limits are poisoned");
+    limits.retain(|_, limit| limit.strong_count() > 0);
+
+    if let Some(limit) = limits.get(base_url).and_then(Weak::upgrade) {
+        return limit;
+    }
+
+    let limit = Arc::new(Semaphore::new(MAX_CONCURRENT_REQUESTS));
+    limits.insert(base_url.to_string(), Arc::downgrade(&limit));
+    limit
+}
+
+/// A request to the Authentik API.
+/// Idempotent requests are retried with a backoff when the server is unavailable.
+#[derive(Debug)]
+pub struct AkRequest {
+    builder: RequestBuilder,
+    method: Method,
+    route: String,
+    idempotent: bool,
+    limit: Arc<Semaphore>,
+    registry_key: Option<String>,
+}
+
+impl AkRequest {
+    pub(super) fn new(
+        builder: RequestBuilder,
+        method: &Method,
+        limit: Arc<Semaphore>,
+        path: &str,
+        registry_key: Option<String>,
+    ) -> Self {
+        Self {
+            builder,
+            method: method.clone(),
+            route: route(path),
+            idempotent: method.is_idempotent(),
+            limit,
+            registry_key,
+        }
+    }
+
+    pub fn query<T: Serialize + ?Sized>(self, query: &T) -> Self {
+        Self {
+            builder: self.builder.query(query),
+            ..self
+        }
+    }
+
+    pub fn json<T: Serialize + ?Sized>(self, json: &T) -> Self {
+        Self {
+            builder: self.builder.json(json),
+            ..self
+        }
+    }
+
+    pub async fn send(self) -> Result<Response, reqwest::Error> {
+        let registry_key = self.registry_key.clone();
+        let (method, route) = (self.method.clone(), self.route.clone());
+        let started = Instant::now();
+        let result = self.send_with_retries().await;
+
+        let status = result.as_ref().ok().map(|res| res.status().as_u16());
+        metrics::api_request(method.as_str(), &route, status, started.elapsed());
+
+        // The token is no longer valid, make sure it's checked again before the next use.
+        if let (Ok(res), Some(key)) = (&result, registry_key) {
+            if res.status() == StatusCode::FORBIDDEN {
+                registry::invalidate(&key);
+            }
+        }
+
+        result
+    }
+
+    async fn send_with_retries(self) -> Result<Response, reqwest::Error> {
+        let started = Instant::now();
+        let mut attempt = 0;
+        loop {
+            attempt += 1;
+
+            // Requests with a body which can't be cloned are never retried.
+            let builder = match self.builder.try_clone() {
+                Some(builder) if self.idempotent && attempt < MAX_ATTEMPTS => builder,
+                _ => return send_limited(&self.limit, self.builder).await,
+            };
+
+            // Timeouts aren't retried, as every attempt could take the full timeout of the client.
+            let result = send_limited(&self.limit, builder).await;
+            let delay = match &result {
+                Ok(res) if is_retryable(res.status()) => {
+                    retry_after(res).unwrap_or_else(|| backoff(attempt))
+                }
+                Err(e) if e.is_connect() => backoff(attempt),
+                _ => return result,
+            };
+
+            if started.elapsed() + delay > RETRY_MAX {
+                return result;
+            }
+
+            debug!(
+                "Authentik API is unavailable, retrying in {:?} (attempt {}).",
+                delay, attempt
+            );
+            tokio::time::sleep(delay).await;
+        }
+    }
+}
+
+/// Send the request, while holding a permit of the instance.
+/// The permit is released before waiting on a retry, so other requests can continue.
+async fn send_limited(
+    limit: &Semaphore,
+    builder: RequestBuilder,
+) -> Result<Response, reqwest::Error> {
+    let _permit = limit
+        .acquire()
+        .await
+        .expect("Request limit semaphore is closed");
+
+    builder.send().await
+}
+
+/// The route of the path, with the ID's replaced to keep the amount of metrics bounded.
+/// For example `/api/v3/core/users/12/set_password/` becomes `/api/v3/core/users/{id}/set_password/`.
+fn route(path: &str) -> String {
+    let path = path.split('?').next().unwrap_or_default();
+    path.split('/')
+        .enumerate()
+        .map(|(i, segment)| match i {
+            5 if !segment.is_empty() => "{id}",
+            _ => segment,
+        })
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+fn is_retryable(status: StatusCode) -> bool {
+    status.is_server_error() || status == StatusCode::TOO_MANY_REQUESTS
+}
+
+/// The delay requested by the server, only supports the amount of seconds.
+fn retry_after(res: &Response) -> Option<Duration> {
+    let seconds = res
+        .headers()
+        .get(RETRY_AFTER)?
+        .to_str()
+        .ok()?
+        .parse()
+        .ok()?;
+
+    Some(Duration::from_secs(seconds).min(BACKOFF_MAX))
+}
+
+/// Exponential backoff with full jitter.
+fn backoff(attempt: u32) -> Duration {
+    let max = BACKOFF_BASE
+        .saturating_mul(2u32.saturating_pow(attempt - 1))
+        .min(BACKOFF_MAX);
+
+    thread_rng().gen_range(Duration::ZERO..=max)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        io::{Read, Write},
+        net::TcpListener,
+        sync::{Arc, Mutex},
+        thread,
+        time::{Duration, Instant},
+    };
+
+    use reqwest::{Method, StatusCode};
+    use tokio::sync::Semaphore;
+
+    use super::{backoff, route, AkRequest, BACKOFF_BASE, BACKOFF_MAX};
+
+    /// Serve the responses in order, one per connection, and record the method of each request.
+    fn serve(responses: Vec<&'static str>) -> (String, Arc<Mutex<Vec<String>>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let requests = Arc::new(Mutex::new(Vec::new()));
+
+        let received = requests.clone();
+        thread::spawn(move || {
+            for response in responses {
+                let (mut stream, _) = listener.accept().unwrap();
+                let mut head = Vec::new();
+                let mut buf = [0; 1024];
+                while !head.windows(4).any(|w| w == b"\r\n\r\n") {
+                    let read = stream.read(&mut buf).unwrap();
+                    head.extend_from_slice(&buf[..read]);
+                }
+
+                let head = String::from_utf8_lossy(&head);
+                let method = head.split(' ').next().unwrap_or_default().to_string();
+                received.lock().unwrap().push(method);
+
+                write!(
+                    stream,
+                    "HTTP/1.1 {}\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+                    response
+                )
+                .unwrap();
+            }
+        });
+
+        (url, requests)
+    }
+
+    fn request(method: Method, url: &str, limit: Arc<Semaphore>) -> AkRequest {
+        let builder = reqwest::Client::new().request(method.clone(), url);
+        AkRequest::new(builder, &method, limit, "/api/v3/core/users/", None)
+    }
+
+    #[tokio::test]
+    async fn retries_idempotent_requests() {
+        let (url, requests) = serve(vec!["503 Service Unavailable", "502 Bad Gateway", "200 OK"]);
+
+        let res = request(Method::GET, &url, Arc::new(Semaphore::new(1)))
+            .send()
+            .await
+            .unwrap();
+
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(*requests.lock().unwrap(), vec!["GET", "GET", "GET"]);
+    }
+
+    #[tokio::test]
+    async fn does_not_retry_other_requests() {
+        let (url, requests) = serve(vec!["503 Service Unavailable", "200 OK"]);
+
+        let res = request(Method::POST, &url, Arc::new(Semaphore::new(1)))
+            .send()
+            .await
+            .unwrap();
+
+        assert_eq!(res.status(), StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(*requests.lock().unwrap(), vec!["POST"]);
+    }
+
+    #[tokio::test]
+    async fn does_not_retry_client_errors() {
+        let (url, requests) = serve(vec!["404 Not Found", "200 OK"]);
+
+        let res = request(Method::GET, &url, Arc::new(Semaphore::new(1)))
+            .send()
+            .await
+            .unwrap();
+
+        assert_eq!(res.status(), StatusCode::NOT_FOUND);
+        assert_eq!(*requests.lock().unwrap(), vec!["GET"]);
+    }
+
+    #[tokio::test]
+    async fn waits_for_retry_after_on_too_many_requests() {
+        let (url, requests) = serve(vec!["429 Too Many Requests\r\nRetry-After: 1", "200 OK"]);
+
+        let started = Instant::now();
+        let res = request(Method::GET, &url, Arc::new(Semaphore::new(1)))
+            .send()
+            .await
+            .unwrap();
+
+        assert_eq!(res.status(), StatusCode::OK);
+        assert!(started.elapsed() >= Duration::from_secs(1));
+        assert_eq!(*requests.lock().unwrap(), vec!["GET", "GET"]);
+    }
+
+    #[tokio::test]
+    async fn releases_the_permit_between_attempts() {
+        let (url, requests) = serve(vec![
+            "503 Service Unavailable\r\nRetry-After: 1",
+            "201 Created",
+            "200 OK",
+        ]);
+        let limit = Arc::new(Semaphore::new(1));
+
+        let retried = tokio::spawn(request(Method::GET, &url, limit.clone()).send());
+        while requests.lock().unwrap().is_empty() {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+
+        // Only one request can run at a time, so this one would wait for the retry if the permit was held.
+        let res = tokio::time::timeout(
+            Duration::from_millis(500),
+            request(Method::POST, &url, limit).send(),
+        )
+        .await
+        .expect("Permit is held while waiting on the retry")
+        .unwrap();
+        assert_eq!(res.status(), StatusCode::CREATED);
+
+        let res = retried.await.unwrap().unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(*requests.lock().unwrap(), vec!["GET", "POST", "GET"]);
+    }
+
+    #[test]
+    fn route_replaces_the_id() {
+        assert_eq!(
+            route("/api/v3/core/users/12/set_password/"),
+            "/api/v3/core/users/{id}/set_password/"
+        );
+        assert_eq!(
+            route("/api/v3/core/groups/5d1a2b/"),
+            "/api/v3/core/groups/{id}/"
+        );
+    }
+
+    #[test]
+    fn route_keeps_list_routes() {
+        assert_eq!(route("/api/v3/core/users/"), "/api/v3/core/users/");
+        assert_eq!(
+            route("/api/v3/core/users/?username=ak-operator"),
+            "/api/v3/core/users/"
+        );
+    }
+
+    #[test]
+    fn backoff_grows_up_to_the_maximum() {
+        for attempt in 1..10 {
+            let max = (BACKOFF_BASE * 2u32.pow(attempt - 1)).min(BACKOFF_MAX);
+            let delays: Vec<_> = (0..200).map(|_| backoff(attempt)).collect();
+
+            assert!(delays.iter().all(|delay| *delay <= max));
+            // Jittered over the whole range, so the delays grow past the maximum of the previous attempt.
+            assert!(delays.iter().any(|delay| *delay > max / 2));
+            assert!(delays.iter().any(|delay| *delay < max / 2));
+        }
+
+        // Large attempts saturate at the maximum, instead of overflowing.
+        let delays: Vec<_> = (0..200).map(|_| backoff(u32::MAX)).collect();
+        assert!(delays.iter().all(|delay| *delay <= BACKOFF_MAX));
+        assert!(delays.iter().any(|delay| *delay > BACKOFF_MAX / 2));
+    }
+}
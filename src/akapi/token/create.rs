@@ -3,7 +3,7 @@ use reqwest::StatusCode;
 use serde::Serialize;
 use thiserror::Error;
 
-use crate::akapi::{types::Token, AkApiRoute, AkClient};
+use crate::akapi::{error::ApiError, types::Token, AkApiRoute, AkClient};
 
 pub struct CreateToken;
 
@@ -23,11 +23,15 @@ impl AkApiRoute for CreateToken {
 
                 Ok(body)
             }
-            StatusCode::BAD_REQUEST => Err(Self::Error::ExistsError),
-            code => Err(Self::Error::Unknown(format!(
-                "Invalid status code {}",
-                code
-            ))),
+            StatusCode::BAD_REQUEST => {
+                let error = ApiError::from_response(res).await;
+                if error.is_unique_violation() {
+                    Err(Self::Error::ExistsError)
+                } else {
+                    Err(error.into())
+                }
+            }
+            _ => Err(ApiError::from_response(res).await.into()),
         }
     }
 }
@@ -45,8 +49,8 @@ pub struct CreateTokenBody {
 pub enum CreateTokenError {
     #[error("The token already exists!")]
     ExistsError,
-    #[error("An unknown error occured ({0}).")]
-    Unknown(String),
+    #[error(transparent)]
+    ApiError(#[from] ApiError),
     #[error("Failed to send HTTP request: {0}")]
     ConnectionError(#[from] reqwest::Error),
 }
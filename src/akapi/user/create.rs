@@ -3,7 +3,7 @@ use reqwest::StatusCode;
 use serde::Serialize;
 use thiserror::Error;
 
-use crate::akapi::{types::User, AkApiRoute, AkClient};
+use crate::akapi::{error::ApiError, types::User, AkApiRoute, AkClient};
 
 pub struct CreateAccount;
 
@@ -23,10 +23,7 @@ impl AkApiRoute for CreateAccount {
 
                 Ok(body)
             }
-            code => Err(Self::Error::Unknown(format!(
-                "Invalid status code {}",
-                code
-            ))),
+            _ => Err(ApiError::from_response(res).await.into()),
         }
     }
 }
@@ -42,8 +39,8 @@ pub struct CreateAccountBody {
 
 #[derive(Error, Debug)]
 pub enum CreateAccountError {
-    #[error("An unknown error occured ({0}).")]
-    Unknown(String),
+    #[error(transparent)]
+    ApiError(#[from] ApiError),
     #[error("Failed to send HTTP request: {0}")]
     ConnectionError(#[from] reqwest::Error),
 }
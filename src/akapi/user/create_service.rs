@@ -3,7 +3,7 @@ use reqwest::StatusCode;
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
 
-use crate::akapi::{AkApiRoute, AkClient};
+use crate::akapi::{error::ApiError, AkApiRoute, AkClient};
 
 pub struct CreateServiceAccount;
 
@@ -27,11 +27,15 @@ impl AkApiRoute for CreateServiceAccount {
 
                 Ok(body)
             }
-            StatusCode::BAD_REQUEST => Err(Self::Error::ExistsError),
-            code => Err(Self::Error::Unknown(format!(
-                "Invalid status code {}",
-                code
-            ))),
+            StatusCode::BAD_REQUEST => {
+                let error = ApiError::from_response(res).await;
+                if error.is_unique_violation() {
+                    Err(Self::Error::ExistsError)
+                } else {
+                    Err(error.into())
+                }
+            }
+            _ => Err(ApiError::from_response(res).await.into()),
         }
     }
 }
@@ -54,8 +58,8 @@ pub struct CreateServiceAccountResponse {
 pub enum CreateServiceAccountError {
     #[error("The user probably already exists!")]
     ExistsError,
-    #[error("An unknown error occured ({0}).")]
-    Unknown(String),
+    #[error(transparent)]
+    ApiError(#[from] ApiError),
     #[error("Failed to send HTTP request: {0}")]
     ConnectionError(#[from] reqwest::Error),
 }
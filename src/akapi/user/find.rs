@@ -1,9 +1,7 @@
 use async_trait::async_trait;
-use reqwest::StatusCode;
-use serde::Deserialize;
 use thiserror::Error;
 
-use crate::akapi::{types::User, AkApiRoute, AkClient};
+use crate::akapi::{error::ApiError, types::User, AkApiRoute, AkClient};
 
 pub struct Find;
 
@@ -15,7 +13,7 @@ impl AkApiRoute for Find {
 
     #[instrument]
     async fn send(ak: &AkClient, body: Self::Body) -> Result<Self::Response, Self::Error> {
-        let mut query = vec![("page_size", "1000".to_string())];
+        let mut query = vec![];
 
         if let Some(name) = body.name {
             query.push(("name", name));
@@ -27,19 +25,7 @@ impl AkApiRoute for Find {
             query.push(("uuid", uuid));
         }
 
-        let res = ak.get("/api/v3/core/users/").query(&query).send().await?;
-
-        match res.status() {
-            StatusCode::OK => {
-                let body: FindResponse = res.json().await?;
-
-                Ok(body.results)
-            }
-            code => Err(Self::Error::Unknown(format!(
-                "Invalid status code {}",
-                code
-            ))),
-        }
+        ak.list("/api/v3/core/users/", &query).await
     }
 }
 
@@ -50,15 +36,10 @@ pub struct FindBody {
     pub uuid: Option<String>,
 }
 
-#[derive(Debug, Deserialize)]
-pub struct FindResponse {
-    pub results: Vec<User>,
-}
-
 #[derive(Error, Debug)]
 pub enum FindError {
-    #[error("An unknown error occured ({0}).")]
-    Unknown(String),
+    #[error(transparent)]
+    ApiError(#[from] ApiError),
     #[error("Failed to send HTTP request: {0}")]
     ConnectionError(#[from] reqwest::Error),
 }
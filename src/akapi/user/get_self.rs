@@ -3,7 +3,7 @@ use reqwest::StatusCode;
 use serde::Deserialize;
 use thiserror::Error;
 
-use crate::akapi::{types::User, AkApiRoute, AkClient};
+use crate::akapi::{error::ApiError, types::User, AkApiRoute, AkClient};
 
 pub struct GetSelf;
 
@@ -24,10 +24,7 @@ impl AkApiRoute for GetSelf {
                 Ok(body)
             }
             StatusCode::FORBIDDEN => Err(Self::Error::Forbidden),
-            code => Err(Self::Error::Unknown(format!(
-                "Invalid status code {}",
-                code
-            ))),
+            _ => Err(ApiError::from_response(res).await.into()),
         }
     }
 }
@@ -42,8 +39,8 @@ pub struct GetSelfResponse {
 pub enum GetSelfError {
     #[error("Server denied our authentication.")]
     Forbidden,
-    #[error("An unknown error occured ({0}).")]
-    Unknown(String),
+    #[error(transparent)]
+    ApiError(#[from] ApiError),
     #[error("Failed to send HTTP request: {0}")]
     ConnectionError(#[from] reqwest::Error),
 }
@@ -3,7 +3,7 @@ use reqwest::StatusCode;
 use serde::Serialize;
 use thiserror::Error;
 
-use crate::akapi::{types::User, AkApiRoute, AkClient};
+use crate::akapi::{error::ApiError, types::User, AkApiRoute, AkClient};
 
 pub struct UpdateUser;
 
@@ -27,10 +27,7 @@ impl AkApiRoute for UpdateUser {
 
                 Ok(body)
             }
-            code => Err(Self::Error::Unknown(format!(
-                "Invalid status code {}",
-                code
-            ))),
+            _ => Err(ApiError::from_response(res).await.into()),
         }
     }
 }
@@ -43,8 +40,8 @@ pub struct UpdateUserBody {
 
 #[derive(Error, Debug)]
 pub enum UpdateUserError {
-    #[error("An unknown error occured ({0}).")]
-    Unknown(String),
+    #[error(transparent)]
+    ApiError(#[from] ApiError),
     #[error("Failed to send HTTP request: {0}")]
     ConnectionError(#[from] reqwest::Error),
 }
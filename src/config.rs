@@ -0,0 +1,185 @@
+use std::{env, time::Duration};
+
+use lazy_static::lazy_static;
+
+lazy_static! {
+    pub static ref CONFIG: Config = Config::from_env();
+}
+
+/// Configuration of the operator, read from environment variables.
+#[derive(Debug, Clone)]
+pub struct Config {
+    /// Interval after which a successfully reconciled object is reconciled again.
+    pub resync_interval: Duration,
+    /// Delay before retrying the first failure of an object.
+    pub backoff_min: Duration,
+    /// Upper bound of the delay between retries.
+    pub backoff_max: Duration,
+    /// Maximum number of objects of each kind reconciled at the same time.
+    /// Every kind has its own limit, which is shared by the controllers of all watched namespaces.
+    pub max_concurrent_reconciles: usize,
+    /// Name of the lease used for leader election.
+    pub lease_name: String,
+    /// Namespaces the controllers watch.
+    pub watch_namespaces: WatchNamespaces,
+    /// Give the instances roles within their namespace instead of cluster roles.
+    /// Only supported when the watched namespaces are listed.
+    pub namespaced_rbac: bool,
+    /// Label selector for the instances this operator is responsible for.
+    pub shard_selector: Option<String>,
+}
+
+/// The namespaces watched by the operator.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WatchNamespaces {
+    All,
+    List(Vec<String>),
+    /// Label selector for the namespaces, resolved when the operator starts.
+    Selector(String),
+}
+
+impl WatchNamespaces {
+    /// Parse a comma separated list of namespaces, or a label selector.
+    /// The selector takes precedence when both are set.
+    fn parse(namespaces: &str, selector: &str) -> Self {
+        let selector = selector.trim();
+        if !selector.is_empty() {
+            return Self::Selector(selector.to_string());
+        }
+
+        let namespaces: Vec<String> = namespaces
+            .split(',')
+            .map(|ns| ns.trim().to_string())
+            .filter(|ns| !ns.is_empty())
+            .collect();
+
+        match namespaces.is_empty() {
+            true => Self::All,
+            false => Self::List(namespaces),
+        }
+    }
+}
+
+impl Config {
+    fn from_env() -> Self {
+        Self {
+            resync_interval: duration_var("RESYNC_INTERVAL", Duration::from_secs(30 * 60)),
+            backoff_min: duration_var("ERROR_BACKOFF_MIN", Duration::from_secs(5)),
+            backoff_max: duration_var("ERROR_BACKOFF_MAX", Duration::from_secs(15 * 60)),
+            max_concurrent_reconciles: number_var("MAX_CONCURRENT_RECONCILES", 10),
+            lease_name: env::var("LEASE_NAME").unwrap_or("authentik-operator".to_string()),
+            watch_namespaces: WatchNamespaces::parse(
+                &env::var("WATCH_NAMESPACES").unwrap_or_default(),
+                &env::var("WATCH_NAMESPACE_SELECTOR").unwrap_or_default(),
+            ),
+            namespaced_rbac: env::var("NAMESPACED_RBAC")
+                .map(|value| value.trim() == "true")
+                .unwrap_or(false),
+            shard_selector: env::var("SHARD_SELECTOR")
+                .ok()
+                .map(|selector| selector.trim().to_string())
+                .filter(|selector| !selector.is_empty()),
+        }
+    }
+}
+
+/// Read a duration from an environment variable, falling back to the default if it's unset or invalid.
+fn duration_var(name: &str, default: Duration) -> Duration {
+    let value = match env::var(name) {
+        Ok(value) => value,
+        Err(_) => return default,
+    };
+
+    parse_duration(&value).unwrap_or_else(|| {
+        warn!(
+            "Invalid duration `{}` for `{}`, using the default.",
+            value, name
+        );
+        default
+    })
+}
+
+/// Read a positive number from an environment variable, falling back to the default if it's unset or invalid.
+fn number_var(name: &str, default: usize) -> usize {
+    let value = match env::var(name) {
+        Ok(value) => value,
+        Err(_) => return default,
+    };
+
+    match value.trim().parse::<usize>() {
+        Ok(number) if number > 0 => number,
+        _ => {
+            warn!(
+                "Invalid number `{}` for `{}`, using the default.",
+                value, name
+            );
+            default
+        }
+    }
+}
+
+/// Parse a duration like `90`, `90s`, `15m` or `2h`. A number without unit is in seconds.
+pub fn parse_duration(value: &str) -> Option<Duration> {
+    let value = value.trim();
+    let (number, multiplier) = match value.chars().last()? {
+        's' => (&value[..value.len() - 1], 1),
+        'm' => (&value[..value.len() - 1], 60),
+        'h' => (&value[..value.len() - 1], 60 * 60),
+        _ => (value, 1),
+    };
+
+    let seconds = number.parse::<u64>().ok()?.checked_mul(multiplier)?;
+    if seconds == 0 {
+        return None;
+    }
+
+    Some(Duration::from_secs(seconds))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::{parse_duration, WatchNamespaces};
+
+    #[test]
+    fn parses_durations() {
+        assert_eq!(parse_duration("90"), Some(Duration::from_secs(90)));
+        assert_eq!(parse_duration("90s"), Some(Duration::from_secs(90)));
+        assert_eq!(parse_duration(" 15m "), Some(Duration::from_secs(15 * 60)));
+        assert_eq!(parse_duration("2h"), Some(Duration::from_secs(2 * 60 * 60)));
+    }
+
+    #[test]
+    fn rejects_invalid_durations() {
+        assert_eq!(parse_duration(""), None);
+        assert_eq!(parse_duration("0s"), None);
+        assert_eq!(parse_duration("m"), None);
+        assert_eq!(parse_duration("-5m"), None);
+        assert_eq!(parse_duration("5d"), None);
+        assert_eq!(parse_duration(&format!("{}h", u64::MAX)), None);
+    }
+
+    #[test]
+    fn parses_watch_namespaces() {
+        assert_eq!(WatchNamespaces::parse("", ""), WatchNamespaces::All);
+        assert_eq!(WatchNamespaces::parse(" , ", ""), WatchNamespaces::All);
+        assert_eq!(
+            WatchNamespaces::parse("a, b,,", ""),
+            WatchNamespaces::List(vec!["a".to_string(), "b".to_string()])
+        );
+    }
+
+    #[test]
+    fn parses_watch_namespace_selectors() {
+        // A bare key is a valid selector, and must not be taken for a namespace.
+        assert_eq!(
+            WatchNamespaces::parse("", "tenant"),
+            WatchNamespaces::Selector("tenant".to_string())
+        );
+        assert_eq!(
+            WatchNamespaces::parse("a", " team=a "),
+            WatchNamespaces::Selector("team=a".to_string())
+        );
+    }
+}
@@ -27,4 +27,9 @@ fn main() {
         )
         .unwrap()
     );
+    print!(
+        "{}",
+        serde_yaml::to_string(&resources::authentik_connection::crd::AuthentikConnection::crd())
+            .unwrap()
+    );
 }
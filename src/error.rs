@@ -26,4 +26,12 @@ pub enum StartError {
     IOError(#[from] std::io::Error),
     #[error("Failed to initialize tracing logger")]
     TracingError,
+    #[error("Lost the leader election.")]
+    LeadershipLost,
+    #[error("The {0} manager exited.")]
+    ManagerExited(String),
+    #[error("No namespaces match the selector `{0}`.")]
+    NoNamespaces(String),
+    #[error("Namespaced RBAC requires the namespaces to be listed in WATCH_NAMESPACES.")]
+    NamespacedRbac,
 }
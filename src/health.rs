@@ -0,0 +1,35 @@
+use std::{collections::BTreeMap, sync::Mutex};
+
+use lazy_static::lazy_static;
+
+lazy_static! {
+    static ref MANAGERS: Mutex<BTreeMap<String, bool>> = Mutex::new(BTreeMap::new());
+}
+
+/// Mark the manager as running.
+pub fn started(name: &str) {
+    MANAGERS
+        .lock()
+        .expect("Manager states are poisoned")
+        .insert(name.to_string(), true);
+}
+
+/// Mark the manager as stopped, which makes the operator unhealthy.
+pub fn stopped(name: &str) {
+    MANAGERS
+        .lock()
+        .expect("Manager states are poisoned")
+        .insert(name.to_string(), false);
+}
+
+/// The managers which have been started, but are no longer running.
+/// Empty when this replica isn't the leader, as it doesn't run any managers.
+pub fn stopped_managers() -> Vec<String> {
+    MANAGERS
+        .lock()
+        .expect("Manager states are poisoned")
+        .iter()
+        .filter(|(_, running)| !**running)
+        .map(|(name, _)| name.clone())
+        .collect()
+}
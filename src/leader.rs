@@ -0,0 +1,154 @@
+use std::{env, time::Duration};
+
+use chrono::Utc;
+use k8s_openapi::{
+    api::coordination::v1::{Lease, LeaseSpec},
+    apimachinery::pkg::apis::meta::v1::MicroTime,
+};
+use kube::{api::PostParams, Api, Client};
+use serde_json::json;
+use tokio::time::{sleep, timeout, Instant};
+
+use crate::{config::CONFIG, resources::generated::random_string};
+
+// How long the lease is valid after it has been renewed.
+static LEASE_DURATION: Duration = Duration::from_secs(15);
+// Interval in which the leader renews the lease.
+static RENEW_INTERVAL: Duration = Duration::from_secs(5);
+// The leader steps down when it couldn't renew the lease for this long.
+// It's shorter than the lease duration, so the managers stop before another replica can take over.
+static RENEW_DEADLINE: Duration = Duration::from_secs(10);
+// Interval in which the other replicas try to acquire the lease.
+static RETRY_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Leader election through a `coordination.k8s.io` lease in the namespace of the operator.
+/// Only one replica holds the lease at a time, the others wait until it expires.
+pub struct LeaderElection {
+    api: Api<Lease>,
+    identity: String,
+}
+
+impl LeaderElection {
+    pub fn new(client: Client) -> Self {
+        let identity = env::var("POD_NAME")
+            .or_else(|_| env::var("HOSTNAME"))
+            .unwrap_or_else(|_| random_string(16));
+
+        Self {
+            api: Api::default_namespaced(client),
+            identity,
+        }
+    }
+
+    /// Wait until this replica is the leader.
+    pub async fn acquire(&self) {
+        info!(
+            "Waiting to acquire the lease `{}` as `{}`.",
+            CONFIG.lease_name, self.identity
+        );
+
+        loop {
+            match self.try_acquire().await {
+                Ok(true) => break,
+                Ok(false) => {}
+                Err(e) => warn!("Failed to acquire the lease: {}", e),
+            }
+
+            sleep(RETRY_INTERVAL).await;
+        }
+
+        info!("Acquired the lease, this replica is now the leader.");
+    }
+
+    /// Keep renewing the lease, returns once the leadership is lost.
+    pub async fn hold(&self) {
+        let mut renewed = Instant::now();
+
+        loop {
+            sleep(RENEW_INTERVAL).await;
+
+            // The lease counts from the start of the request, a slow request doesn't extend it.
+            let attempt = Instant::now();
+            let remaining = RENEW_DEADLINE.saturating_sub(renewed.elapsed());
+            match timeout(remaining, self.try_acquire()).await {
+                Ok(Ok(true)) => renewed = attempt,
+                Ok(Ok(false)) => return,
+                Ok(Err(e)) => warn!("Failed to renew the lease: {}", e),
+                Err(_) => warn!("Timed out renewing the lease."),
+            }
+
+            if renewed.elapsed() >= RENEW_DEADLINE {
+                return;
+            }
+        }
+    }
+
+    /// Try to take or renew the lease once, returns if this replica holds it.
+    async fn try_acquire(&self) -> Result<bool, kube::Error> {
+        let now = Utc::now();
+        let lease = match self.api.get_opt(&CONFIG.lease_name).await? {
+            Some(lease) => lease,
+            None => {
+                let lease: Lease = serde_json::from_value(json!({
+                    "apiVersion": "coordination.k8s.io/v1",
+                    "kind": "Lease",
+                    "metadata": {
+                        "name": CONFIG.lease_name,
+                    },
+                }))
+                .expect("Failed to build the lease");
+
+                return self.write(lease, now, true).await;
+            }
+        };
+
+        let spec = lease.spec.clone().unwrap_or_default();
+        let held = spec.holder_identity.as_deref() == Some(&self.identity);
+        let expired = match (spec.renew_time.as_ref(), spec.lease_duration_seconds) {
+            (Some(renewed), Some(duration)) => {
+                renewed.0 + chrono::Duration::seconds(duration.into()) < now
+            }
+            _ => true,
+        };
+
+        if !held && !expired {
+            return Ok(false);
+        }
+
+        self.write(lease, now, !held).await
+    }
+
+    /// Write the lease with this replica as holder.
+    /// Conflicts mean another replica was faster, which isn't an error.
+    async fn write(
+        &self,
+        mut lease: Lease,
+        now: chrono::DateTime<Utc>,
+        taking_over: bool,
+    ) -> Result<bool, kube::Error> {
+        let creating = lease.metadata.resource_version.is_none();
+        let spec = lease.spec.get_or_insert_with(LeaseSpec::default);
+        spec.holder_identity = Some(self.identity.clone());
+        spec.lease_duration_seconds = Some(LEASE_DURATION.as_secs() as i32);
+        spec.renew_time = Some(MicroTime(now));
+        if taking_over {
+            spec.acquire_time = Some(MicroTime(now));
+            spec.lease_transitions =
+                Some(spec.lease_transitions.unwrap_or(0) + i32::from(!creating));
+        }
+
+        let result = if creating {
+            self.api.create(&PostParams::default(), &lease).await
+        } else {
+            self.api
+                .replace(&CONFIG.lease_name, &PostParams::default(), &lease)
+                .await
+        };
+
+        match result {
+            Ok(_) => Ok(true),
+            Err(kube::Error::Api(e)) if e.code == 409 => Ok(false),
+            Err(e) => Err(e),
+        }
+    }
+}
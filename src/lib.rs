@@ -2,7 +2,11 @@
 extern crate tracing;
 
 pub mod akapi;
+pub mod config;
 mod error;
+pub mod health;
+pub mod leader;
+pub mod metrics;
 pub mod resources;
 
 pub use error::*;
@@ -1,20 +1,74 @@
 #[macro_use]
 extern crate tracing;
 
-use actix_web::{get, middleware, App, HttpRequest, HttpResponse, HttpServer, Responder};
+use std::time::Duration;
+
+use actix_web::{
+    get, middleware, web::Data, App, HttpRequest, HttpResponse, HttpServer, Responder,
+};
+use futures::{
+    future::{self, BoxFuture},
+    FutureExt,
+};
+use k8s_openapi::api::core::v1::Namespace;
 use k8s_openapi::apiextensions_apiserver::pkg::apis::apiextensions::v1::CustomResourceDefinition;
 use kube::{
-    api::{Patch, PatchParams},
+    api::{ListParams, Patch, PatchParams},
     Api, Client, CustomResourceExt, ResourceExt,
 };
 use tracing_subscriber::{prelude::*, EnvFilter, Registry};
 
+use akcontroller::config::{WatchNamespaces, CONFIG};
+use akcontroller::health::stopped_managers;
+use akcontroller::leader::LeaderElection;
 use akcontroller::resources;
 use akcontroller::StartError;
 
+/// Alive as long as none of the managers stopped.
+fn liveness() -> HttpResponse {
+    let stopped = stopped_managers();
+    if !stopped.is_empty() {
+        return HttpResponse::ServiceUnavailable()
+            .json(format!("Stopped managers: {}", stopped.join(", ")));
+    }
+
+    HttpResponse::Ok().json("alive")
+}
+
+#[get("/livez")]
+async fn livez(_: HttpRequest) -> impl Responder {
+    liveness()
+}
+
+/// Kept for existing probes, same as `/livez`.
 #[get("/health")]
 async fn health(_: HttpRequest) -> impl Responder {
-    HttpResponse::Ok().json("healthy")
+    liveness()
+}
+
+/// Ready when the Kubernetes API is reachable and none of the managers stopped.
+#[get("/readyz")]
+async fn readyz(client: Data<Client>) -> impl Responder {
+    let stopped = stopped_managers();
+    if !stopped.is_empty() {
+        return HttpResponse::ServiceUnavailable()
+            .json(format!("Stopped managers: {}", stopped.join(", ")));
+    }
+
+    match tokio::time::timeout(Duration::from_secs(5), client.apiserver_version()).await {
+        Ok(Ok(_)) => HttpResponse::Ok().json("ready"),
+        Ok(Err(e)) => {
+            HttpResponse::ServiceUnavailable().json(format!("Kubernetes API is unreachable: {}", e))
+        }
+        Err(_) => HttpResponse::ServiceUnavailable().json("Kubernetes API timed out."),
+    }
+}
+
+#[get("/metrics")]
+async fn metrics(_: HttpRequest) -> impl Responder {
+    HttpResponse::Ok()
+        .content_type("text/plain; version=0.0.4")
+        .body(akcontroller::metrics::encode())
 }
 
 #[tokio::main]
@@ -26,19 +80,48 @@ async fn main() -> Result<(), StartError> {
 
     let collector = Registry::default().with(logger).with(env_filter);
     tracing::subscriber::set_global_default(collector).map_err(|_| StartError::TracingError)?;
+    info!(
+        "Resyncing every {} seconds, backing off between {} and {} seconds on errors, reconciling up to {} objects at once.",
+        CONFIG.resync_interval.as_secs(),
+        CONFIG.backoff_min.as_secs(),
+        CONFIG.backoff_max.as_secs(),
+        CONFIG.max_concurrent_reconciles
+    );
 
+    let client = Client::try_default().await?;
     let server = HttpServer::new(move || {
         App::new()
-            .wrap(middleware::Logger::default().exclude("/health"))
+            .app_data(Data::new(client.clone()))
+            .wrap(
+                middleware::Logger::default()
+                    .exclude("/health")
+                    .exclude("/livez")
+                    .exclude("/readyz")
+                    .exclude("/metrics"),
+            )
             .service(health)
+            .service(livez)
+            .service(readyz)
+            .service(metrics)
     })
     .bind("0.0.0.0:8080")?
     .shutdown_timeout(5);
 
     ensure_crds().await?;
 
+    // Only the leader runs the managers, the other replicas keep serving the health checks.
+    let leader = LeaderElection::new(Client::try_default().await?);
+    let managers = async {
+        leader.acquire().await;
+
+        tokio::select! {
+            result = start_managers() => result,
+            _ = leader.hold() => Err(StartError::LeadershipLost),
+        }
+    };
+
     tokio::select! {
-        _ = start_managers() => warn!("A manager exited"),
+        result = managers => result?,
         _ = server.run() => warn!("Actix Web exited"),
     }
     Ok(())
@@ -51,6 +134,7 @@ async fn ensure_crds() -> Result<(), StartError> {
         resources::authentik_group::crd::AuthentikGroup::crd(),
         resources::authentik_user::crd::AuthentikUser::crd(),
         resources::authentik_provider_oauth::crd::AuthentikOAuthProvider::crd(),
+        resources::authentik_connection::crd::AuthentikConnection::crd(),
     ];
 
     let client = Client::try_default().await?;
@@ -68,20 +152,83 @@ async fn ensure_crds() -> Result<(), StartError> {
     Ok(())
 }
 
+/// Run all managers for every watched namespace, returns an error as soon as one of them exits.
+/// The process then exits with an error, so Kubernetes restarts it.
 async fn start_managers() -> Result<(), StartError> {
-    let authentik_mgr = resources::AuthentikManager::new(Client::try_default().await?);
-    let authentik_app_mgr = resources::AuthentikAppManager::new(Client::try_default().await?);
-    let authentik_user_mgr = resources::AuthentikUserManager::new(Client::try_default().await?);
-    let authentik_group_mgr = resources::AuthentikGroupManager::new(Client::try_default().await?);
-    let authentik_oauth_mgr = resources::AuthentikOAuthManager::new(Client::try_default().await?);
+    let client = Client::try_default().await?;
 
-    tokio::select! {
-        _ = authentik_mgr => warn!("Authentik controller exited"),
-        _ = authentik_app_mgr => warn!("Authentik user controller exited"),
-        _ = authentik_user_mgr => warn!("Authentik user controller exited"),
-        _ = authentik_group_mgr => warn!("Authentik user controller exited"),
-        _ = authentik_oauth_mgr => warn!("Authentik oauth provider controller exited"),
+    let mut managers = Vec::new();
+    for ns in watched_namespaces(client.clone()).await? {
+        let scope = ns
+            .as_ref()
+            .map_or(String::new(), |ns| format!(" in `{}`", ns));
+
+        managers.push(supervise(
+            format!("authentik{}", scope),
+            resources::AuthentikManager::new(client.clone(), ns.clone()),
+        ));
+        managers.push(supervise(
+            format!("application{}", scope),
+            resources::AuthentikAppManager::new(client.clone(), ns.clone()),
+        ));
+        managers.push(supervise(
+            format!("user{}", scope),
+            resources::AuthentikUserManager::new(client.clone(), ns.clone()),
+        ));
+        managers.push(supervise(
+            format!("group{}", scope),
+            resources::AuthentikGroupManager::new(client.clone(), ns.clone()),
+        ));
+        managers.push(supervise(
+            format!("oauth provider{}", scope),
+            resources::AuthentikOAuthManager::new(client.clone(), ns.clone()),
+        ));
     }
 
-    Ok(())
+    let (name, _, _) = future::select_all(managers).await;
+    Err(StartError::ManagerExited(name))
+}
+
+/// The namespaces to run the managers in, `None` meaning all namespaces.
+/// A label selector is only resolved once, new namespaces are picked up after a restart.
+async fn watched_namespaces(client: Client) -> Result<Vec<Option<String>>, StartError> {
+    // Roles are only given out within the listed namespaces, the chart creates the operator's roles there.
+    if CONFIG.namespaced_rbac && !matches!(CONFIG.watch_namespaces, WatchNamespaces::List(_)) {
+        return Err(StartError::NamespacedRbac);
+    }
+
+    let namespaces = match &CONFIG.watch_namespaces {
+        WatchNamespaces::All => return Ok(vec![None]),
+        WatchNamespaces::List(namespaces) => namespaces.clone(),
+        WatchNamespaces::Selector(selector) => {
+            let api: Api<Namespace> = Api::all(client);
+            let namespaces: Vec<String> = api
+                .list(&ListParams::default().labels(selector))
+                .await?
+                .iter()
+                .map(|ns| ns.name_any())
+                .collect();
+
+            if namespaces.is_empty() {
+                return Err(StartError::NoNamespaces(selector.clone()));
+            }
+            namespaces
+        }
+    };
+
+    info!("Watching the namespaces {}.", namespaces.join(", "));
+    Ok(namespaces.into_iter().map(Some).collect())
+}
+
+/// Keep track of the state of the manager while it runs, returns its name once it exits.
+fn supervise(name: String, manager: BoxFuture<'static, ()>) -> BoxFuture<'static, String> {
+    async move {
+        akcontroller::health::started(&name);
+        manager.await;
+        akcontroller::health::stopped(&name);
+
+        error!("The {} manager exited.", name);
+        name
+    }
+    .boxed()
 }
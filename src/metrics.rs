@@ -0,0 +1,137 @@
+use std::{
+    collections::BTreeMap,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use kube::{runtime::reflector::Store, Resource, ResourceExt};
+use lazy_static::lazy_static;
+use prometheus::{
+    register_histogram_vec, register_int_counter_vec, register_int_gauge_vec, Encoder,
+    HistogramVec, IntCounterVec, IntGaugeVec, TextEncoder,
+};
+
+type ObjectCounter = Box<dyn Fn(&IntGaugeVec) + Send>;
+
+lazy_static! {
+    static ref RECONCILES: IntCounterVec = register_int_counter_vec!(
+        "ak_operator_reconciles_total",
+        "Reconciles per kind.",
+        &["kind"]
+    )
+    .expect("Failed to register metric");
+    static ref RECONCILE_FAILURES: IntCounterVec = register_int_counter_vec!(
+        "ak_operator_reconcile_failures_total",
+        "Failed reconciles per kind.",
+        &["kind"]
+    )
+    .expect("Failed to register metric");
+    static ref RECONCILE_DURATION: HistogramVec = register_histogram_vec!(
+        "ak_operator_reconcile_duration_seconds",
+        "Duration of the reconciles per kind.",
+        &["kind"],
+        vec![0.01, 0.05, 0.1, 0.5, 1.0, 5.0, 15.0, 60.0]
+    )
+    .expect("Failed to register metric");
+    static ref API_REQUESTS: IntCounterVec = register_int_counter_vec!(
+        "ak_operator_api_requests_total",
+        "Requests to the Authentik API per route and status code.",
+        &["method", "route", "status"]
+    )
+    .expect("Failed to register metric");
+    static ref API_REQUEST_DURATION: HistogramVec = register_histogram_vec!(
+        "ak_operator_api_request_duration_seconds",
+        "Duration of the requests to the Authentik API, including retries.",
+        &["method", "route"],
+        vec![0.01, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 10.0, 30.0]
+    )
+    .expect("Failed to register metric");
+    static ref TOKEN_VALIDATION_FAILURES: IntCounterVec = register_int_counter_vec!(
+        "ak_operator_token_validation_failures_total",
+        "API tokens which were rejected by an instance.",
+        &["namespace", "instance"]
+    )
+    .expect("Failed to register metric");
+    static ref MANAGED_OBJECTS: IntGaugeVec = register_int_gauge_vec!(
+        "ak_operator_managed_objects",
+        "Objects managed per instance and kind.",
+        &["namespace", "instance", "kind"]
+    )
+    .expect("Failed to register metric");
+    static ref OBJECT_COUNTERS: Mutex<Vec<ObjectCounter>> = Mutex::new(Vec::new());
+}
+
+/// Record the outcome of a reconcile of the given kind.
+pub fn reconciled<K: Resource<DynamicType = ()>>(started: Instant, succeeded: bool) {
+    let kind = K::kind(&());
+    RECONCILES.with_label_values(&[&kind]).inc();
+    RECONCILE_DURATION
+        .with_label_values(&[&kind])
+        .observe(started.elapsed().as_secs_f64());
+    if !succeeded {
+        RECONCILE_FAILURES.with_label_values(&[&kind]).inc();
+    }
+}
+
+/// Record a request to the Authentik API.
+/// The status is `error` when no response was received.
+pub fn api_request(method: &str, route: &str, status: Option<u16>, duration: Duration) {
+    let status = status.map_or("error".to_string(), |status| status.to_string());
+    API_REQUESTS
+        .with_label_values(&[method, route, &status])
+        .inc();
+    API_REQUEST_DURATION
+        .with_label_values(&[method, route])
+        .observe(duration.as_secs_f64());
+}
+
+/// Record a token which was rejected by an instance.
+pub fn token_rejected(ns: &str, instance: &str) {
+    TOKEN_VALIDATION_FAILURES
+        .with_label_values(&[ns, instance])
+        .inc();
+}
+
+/// Count the objects within the store per instance, each time the metrics are gathered.
+pub fn count_objects<K>(store: Store<K>, instance: fn(&K) -> String)
+where
+    K: Resource<DynamicType = ()> + Clone + Send + Sync + 'static,
+{
+    OBJECT_COUNTERS
+        .lock()
+        .expect("Object counters are poisoned")
+        .push(Box::new(move |gauge| {
+            let mut counts = BTreeMap::new();
+            for obj in store.state() {
+                *counts
+                    .entry((obj.namespace().unwrap_or_default(), instance(&obj)))
+                    .or_insert(0) += 1;
+            }
+
+            for ((ns, instance), count) in counts {
+                gauge
+                    .with_label_values(&[&ns, &instance, &K::kind(&())])
+                    .set(count);
+            }
+        }));
+}
+
+/// Gather all metrics in the Prometheus text format.
+pub fn encode() -> String {
+    // Start from scratch, so deleted objects and instances disappear.
+    MANAGED_OBJECTS.reset();
+    for counter in OBJECT_COUNTERS
+        .lock()
+        .expect("Object counters are poisoned")
+        .iter()
+    {
+        counter(&MANAGED_OBJECTS);
+    }
+
+    let mut buffer = Vec::new();
+    if let Err(e) = TextEncoder::new().encode(&prometheus::gather(), &mut buffer) {
+        warn!("Failed to encode the metrics: {}", e);
+    }
+
+    String::from_utf8(buffer).unwrap_or_default()
+}
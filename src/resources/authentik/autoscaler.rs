@@ -0,0 +1,80 @@
+use anyhow::{anyhow, Result};
+use k8s_openapi::api::autoscaling::v2::HorizontalPodAutoscaler;
+use kube::{Client, ResourceExt};
+use serde_json::{json, Value};
+
+use super::{component, crd, labels};
+
+pub async fn reconcile(obj: &crd::Authentik, client: Client) -> Result<()> {
+    component::reconcile::<HorizontalPodAutoscaler, _>(
+        obj,
+        client,
+        |component| component.autoscaling.as_ref(),
+        validate,
+        build,
+    )
+    .await
+}
+
+pub async fn cleanup(_obj: &crd::Authentik, _client: Client) -> Result<()> {
+    Ok(())
+}
+
+fn validate(component: &str, autoscaling: &crd::AuthentikAutoscaling) -> Result<()> {
+    if autoscaling.min_replicas < 1 {
+        return Err(anyhow!(
+            "minReplicas must be at least 1 for the {} autoscaler.",
+            component
+        ));
+    }
+    if autoscaling.min_replicas > autoscaling.max_replicas {
+        return Err(anyhow!(
+            "minReplicas must not be greater than maxReplicas for the {} autoscaler.",
+            component
+        ));
+    }
+
+    Ok(())
+}
+
+fn build(
+    name: String,
+    obj: &crd::Authentik,
+    component: &str,
+    autoscaling: &crd::AuthentikAutoscaling,
+) -> Value {
+    json!({
+        "apiVersion": "autoscaling/v2",
+        "kind": "HorizontalPodAutoscaler",
+        "metadata": {
+            "name": format!("authentik-{}-{}", name, component),
+            "labels": labels::get_labels(name.clone(), obj.spec.image.tag.to_string(), component.to_string()),
+            "ownerReferences": [{
+                "apiVersion": "ak.dany.dev/v1",
+                "kind": "Authentik",
+                "name": name,
+                "uid": obj.uid().expect("Failed to get UID of Authentik."),
+                "controller": true,
+            }]
+        },
+        "spec": {
+            "scaleTargetRef": {
+                "apiVersion": "apps/v1",
+                "kind": "Deployment",
+                "name": format!("authentik-{}-{}", name, component)
+            },
+            "minReplicas": autoscaling.min_replicas,
+            "maxReplicas": autoscaling.max_replicas,
+            "metrics": [{
+                "type": "Resource",
+                "resource": {
+                    "name": "cpu",
+                    "target": {
+                        "type": "Utilization",
+                        "averageUtilization": autoscaling.target_cpu_utilization_percentage
+                    }
+                }
+            }]
+        }
+    })
+}
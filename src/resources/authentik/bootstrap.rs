@@ -0,0 +1,31 @@
+use anyhow::Result;
+use kube::{Client, ResourceExt};
+
+use crate::resources::generated;
+
+use super::{crd, labels};
+
+pub async fn reconcile(obj: &crd::Authentik, client: Client) -> Result<()> {
+    let instance = obj.name_any();
+
+    // Generate the bootstrap token once, it's only used until the operator has its own token.
+    generated::ensure_secret(
+        client,
+        obj,
+        &format!("ak-{}-bootstrap", instance),
+        labels::get_labels(
+            instance.clone(),
+            obj.spec.image.tag.to_string(),
+            "bootstrap".to_string(),
+        ),
+        &[("token", 64)],
+    )
+    .await?;
+
+    Ok(())
+}
+
+pub async fn cleanup(_obj: &crd::Authentik, _client: Client) -> Result<()> {
+    // Note: The secret will automatically be cleaned up by Kubernetes.
+    Ok(())
+}
@@ -1,7 +1,7 @@
 use anyhow::{anyhow, Result};
 use k8s_openapi::api::{
     core::v1::ServiceAccount,
-    rbac::v1::{ClusterRole, ClusterRoleBinding},
+    rbac::v1::{ClusterRole, ClusterRoleBinding, Role, RoleBinding},
 };
 use kube::{
     api::{DeleteParams, Patch, PatchParams},
@@ -9,6 +9,8 @@ use kube::{
 };
 use serde_json::{json, Value};
 
+use crate::config::CONFIG;
+
 use super::{crd, labels};
 
 pub async fn reconcile(obj: &crd::Authentik, client: Client) -> Result<()> {
@@ -30,6 +32,27 @@ pub async fn reconcile(obj: &crd::Authentik, client: Client) -> Result<()> {
     )
     .await?;
 
+    // A namespaced operator can only give the instance access to its own namespace.
+    if CONFIG.namespaced_rbac {
+        let api: Api<Role> = Api::namespaced(client.clone(), &ns);
+        api.patch(
+            &format!("ak-{}", &instance),
+            &PatchParams::apply("authentik.ak-operator").force(),
+            &Patch::Apply(build_role(instance.clone(), obj)),
+        )
+        .await?;
+
+        let api: Api<RoleBinding> = Api::namespaced(client.clone(), &ns);
+        api.patch(
+            &format!("ak-{}", &instance),
+            &PatchParams::apply("authentik.ak-operator").force(),
+            &Patch::Apply(build_role_binding(instance.clone(), obj)),
+        )
+        .await?;
+
+        return Ok(());
+    }
+
     // Create the cluster role.
     let api: Api<ClusterRole> = Api::all(client.clone());
     api.patch(
@@ -58,6 +81,11 @@ pub async fn cleanup(obj: &crd::Authentik, client: Client) -> Result<()> {
         .clone()
         .ok_or(anyhow!("Missing instance name.".to_string()))?;
 
+    // Note: Roles of a namespaced operator will automatically be cleaned up by Kubernetes.
+    if CONFIG.namespaced_rbac {
+        return Ok(());
+    }
+
     // Clean up cluster resources as owner references don't work.
     let api: Api<ClusterRole> = Api::all(client.clone());
     api.delete(&format!("ak-{}", &instance), &DeleteParams::foreground())
@@ -89,6 +117,13 @@ fn build_serviceaccount(name: String, obj: &crd::Authentik) -> Value {
 }
 
 fn build_clusterrole(name: String, obj: &crd::Authentik) -> Value {
+    let mut rules = rules();
+    rules.push(json!({
+        "apiGroups": ["apiextensions.k8s.io"],
+        "resources": ["customresourcedefinitions"],
+        "verbs": ["*"]
+    }));
+
     json!({
         "apiVersion": "rbac.authorization.k8s.io/v1",
         "kind": "ClusterRole",
@@ -96,41 +131,60 @@ fn build_clusterrole(name: String, obj: &crd::Authentik) -> Value {
             "name": format!("ak-{}", &name),
             "labels": labels::get_labels(name.clone(), obj.spec.image.tag.to_string(), "clusteraccount".to_string())
         },
-        "rules": [
-            {
-                "apiGroups": [""],
-                "resources": ["secrets", "services", "configmaps"],
-                "verbs": ["*"]
-            },
-            {
-                "apiGroups": ["extensions", "apps"],
-                "resources": ["deployments"],
-                "verbs": ["*"]
-            },
-            {
-                "apiGroups": ["extensions", "networking.k8s.io"],
-                "resources": ["ingresses"],
-                "verbs": ["*"]
-            },
-            {
-                "apiGroups": ["traefik.containo.us"],
-                "resources": ["middlewares"],
-                "verbs": ["*"]
-            },
-            {
-                "apiGroups": ["monitoring.coreos.com"],
-                "resources": ["servicemonitors"],
-                "verbs": ["*"]
-            },
-            {
-                "apiGroups": ["apiextensions.k8s.io"],
-                "resources": ["customresourcedefinitions"],
-                "verbs": ["*"]
-            }
-        ]
+        "rules": rules
     })
 }
 
+fn build_role(name: String, obj: &crd::Authentik) -> Value {
+    json!({
+        "apiVersion": "rbac.authorization.k8s.io/v1",
+        "kind": "Role",
+        "metadata": {
+            "name": format!("ak-{}", &name),
+            "labels": labels::get_labels(name.clone(), obj.spec.image.tag.to_string(), "clusteraccount".to_string()),
+            "ownerReferences": [{
+                "apiVersion": "ak.dany.dev/v1",
+                "kind": "Authentik",
+                "name": name,
+                "uid": obj.uid().expect("Failed to get UID of Authentik."),
+                "controller": true,
+            }]
+        },
+        "rules": rules()
+    })
+}
+
+/// Rules for the resources Authentik manages for its outposts.
+fn rules() -> Vec<Value> {
+    vec![
+        json!({
+            "apiGroups": [""],
+            "resources": ["secrets", "services", "configmaps"],
+            "verbs": ["*"]
+        }),
+        json!({
+            "apiGroups": ["extensions", "apps"],
+            "resources": ["deployments"],
+            "verbs": ["*"]
+        }),
+        json!({
+            "apiGroups": ["extensions", "networking.k8s.io"],
+            "resources": ["ingresses"],
+            "verbs": ["*"]
+        }),
+        json!({
+            "apiGroups": ["traefik.containo.us"],
+            "resources": ["middlewares"],
+            "verbs": ["*"]
+        }),
+        json!({
+            "apiGroups": ["monitoring.coreos.com"],
+            "resources": ["servicemonitors"],
+            "verbs": ["*"]
+        }),
+    ]
+}
+
 fn build_binding(name: String, obj: &crd::Authentik, ns: &str) -> Value {
     json!({
         "apiVersion": "rbac.authorization.k8s.io/v1",
@@ -151,3 +205,30 @@ fn build_binding(name: String, obj: &crd::Authentik, ns: &str) -> Value {
         }]
     })
 }
+
+fn build_role_binding(name: String, obj: &crd::Authentik) -> Value {
+    json!({
+        "apiVersion": "rbac.authorization.k8s.io/v1",
+        "kind": "RoleBinding",
+        "metadata": {
+            "name": format!("ak-{}", &name),
+            "labels": labels::get_labels(name.clone(), obj.spec.image.tag.to_string(), "clusteraccount".to_string()),
+            "ownerReferences": [{
+                "apiVersion": "ak.dany.dev/v1",
+                "kind": "Authentik",
+                "name": name,
+                "uid": obj.uid().expect("Failed to get UID of Authentik."),
+                "controller": true,
+            }]
+        },
+        "roleRef": {
+            "apiGroup": "rbac.authorization.k8s.io",
+            "kind": "Role",
+            "name": format!("ak-{}", &name)
+        },
+        "subjects": [{
+            "kind": "ServiceAccount",
+            "name": format!("ak-{}", &name)
+        }]
+    })
+}
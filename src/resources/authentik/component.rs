@@ -0,0 +1,67 @@
+use std::fmt::Debug;
+
+use anyhow::{anyhow, Result};
+use k8s_openapi::NamespaceResourceScope;
+use kube::{
+    api::{DeleteParams, Patch, PatchParams},
+    Api, Client, Resource, ResourceExt,
+};
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+
+use super::crd;
+
+/// Apply an optional object for both the server and the worker, named after their deployment.
+/// The settings of both components are validated before anything is applied.
+/// An object is removed again, once it's no longer defined for its component.
+pub async fn reconcile<K, T>(
+    obj: &crd::Authentik,
+    client: Client,
+    settings: impl Fn(&crd::AuthentikComponent) -> Option<&T>,
+    validate: impl Fn(&str, &T) -> Result<()>,
+    build: impl Fn(String, &crd::Authentik, &str, &T) -> Value,
+) -> Result<()>
+where
+    K: Resource<Scope = NamespaceResourceScope, DynamicType = ()>
+        + Clone
+        + DeserializeOwned
+        + Debug,
+{
+    let instance = obj
+        .metadata
+        .name
+        .clone()
+        .ok_or(anyhow!("Missing instance name.".to_string()))?;
+    let ns = obj
+        .namespace()
+        .ok_or(anyhow!("Missing namespace `{}`.", instance.clone()))?;
+    let api: Api<K> = Api::namespaced(client, &ns);
+
+    let components = [
+        ("server", settings(&obj.spec.server)),
+        ("worker", settings(&obj.spec.worker)),
+    ];
+    for (component, spec) in components {
+        if let Some(spec) = spec {
+            validate(component, spec)?;
+        }
+    }
+
+    for (component, spec) in components {
+        let name = format!("authentik-{}-{}", instance, component);
+
+        if let Some(spec) = spec {
+            api.patch(
+                &name,
+                &PatchParams::apply("authentik.ak-operator").force(),
+                &Patch::Apply(build(instance.clone(), obj, component, spec)),
+            )
+            .await?;
+        } else if api.get_opt(&name).await?.is_some() {
+            // Remove the object, as it's no longer in the CRD defined.
+            api.delete(&name, &DeleteParams::default()).await?;
+        }
+    }
+
+    Ok(())
+}
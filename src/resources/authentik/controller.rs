@@ -1,102 +1,180 @@
 use std::sync::Arc;
 
 use anyhow::{anyhow, Result};
+use async_trait::async_trait;
 use kube::{
-    api::{Api, Patch, PatchParams, ResourceExt},
+    api::{Api, ResourceExt},
     runtime::controller::Action,
     Client,
 };
-use rand::{distributions::Alphanumeric, thread_rng, Rng};
-use serde_json::{json, Map};
 use tokio::time::Duration;
 
+use crate::resources::{
+    events::Recorder,
+    requeue,
+    status::{self, READY},
+    Reconciler,
+};
+
 use super::{
-    clusteraccount, crd, deployment, ingress, secret, service, serviceaccount, servicegroup,
+    autoscaler, bootstrap, clusteraccount,
+    crd::{
+        self, CONDITION_API_TOKEN_VALID, CONDITION_DEPLOYMENTS_AVAILABLE,
+        CONDITION_SERVICE_GROUP_READY,
+    },
+    deployment, disruptionbudget, ingress, rotation, secret, secretkey, service, serviceaccount,
+    servicegroup,
 };
 
 pub struct Controller {
     client: Client,
+    recorder: Recorder,
 }
 
 impl Controller {
     pub fn new(client: Client) -> Self {
-        Self { client }
+        Self {
+            recorder: Recorder::new(client.clone()),
+            client,
+        }
+    }
+
+    async fn reconcile_parts(
+        &self,
+        obj: &crd::Authentik,
+        status: &mut crd::AuthentikStatus,
+    ) -> Result<Option<Action>> {
+        clusteraccount::reconcile(obj, self.client.clone())
+            .await
+            .map_err(|e| status.fail(READY, "ClusterAccountFailed", e))?;
+        bootstrap::reconcile(obj, self.client.clone())
+            .await
+            .map_err(|e| status.fail(READY, "BootstrapFailed", e))?;
+        secretkey::reconcile(obj, self.client.clone())
+            .await
+            .map_err(|e| status.fail(READY, "SecretKeyFailed", e))?;
+        deployment::reconcile(obj, self.client.clone())
+            .await
+            .map_err(|e| status.fail(CONDITION_DEPLOYMENTS_AVAILABLE, "DeploymentFailed", e))?;
+        autoscaler::reconcile(obj, self.client.clone())
+            .await
+            .map_err(|e| status.fail(READY, "AutoscalerFailed", e))?;
+        disruptionbudget::reconcile(obj, self.client.clone())
+            .await
+            .map_err(|e| status.fail(READY, "DisruptionBudgetFailed", e))?;
+        service::reconcile(obj, self.client.clone())
+            .await
+            .map_err(|e| status.fail(READY, "ServiceFailed", e))?;
+        ingress::reconcile(obj, self.client.clone())
+            .await
+            .map_err(|e| status.fail(READY, "IngressFailed", e))?;
+
+        // The API is only reachable once the server is running.
+        let (available, tag) = deployment::availability(obj, self.client.clone())
+            .await
+            .map_err(|e| status.fail(CONDITION_DEPLOYMENTS_AVAILABLE, "AvailabilityFailed", e))?;
+        if tag.is_some() {
+            status.image_tag = tag;
+        }
+        if !available {
+            let msg = "Waiting for the server and worker to become available.";
+            status.set(CONDITION_DEPLOYMENTS_AVAILABLE, false, "Unavailable", msg);
+            status.set(READY, false, "DeploymentsUnavailable", msg);
+
+            // Changes to the deployments will trigger a reconcilidation as well.
+            return Ok(Some(Action::requeue(Duration::from_secs(60))));
+        }
+        status.set(CONDITION_DEPLOYMENTS_AVAILABLE, true, "Available", "");
+
+        serviceaccount::reconcile(obj, self.client.clone(), &self.recorder)
+            .await
+            .map_err(|e| status.fail(CONDITION_API_TOKEN_VALID, "ServiceAccountFailed", e))?;
+        servicegroup::reconcile(obj, self.client.clone(), &self.recorder)
+            .await
+            .map_err(|e| status.fail(CONDITION_SERVICE_GROUP_READY, "ServiceGroupFailed", e))?;
+        status.set(CONDITION_SERVICE_GROUP_READY, true, "Created", "");
+        secret::reconcile(obj, self.client.clone(), &self.recorder)
+            .await
+            .map_err(|e| status.fail(CONDITION_API_TOKEN_VALID, "TokenSecretFailed", e))?;
+        rotation::reconcile(obj, self.client.clone(), &self.recorder, status)
+            .await
+            .map_err(|e| status.fail(CONDITION_API_TOKEN_VALID, "TokenRotationFailed", e))?;
+        status.set(CONDITION_API_TOKEN_VALID, true, "Valid", "");
+
+        Ok(None)
     }
 
-    pub async fn reconcile(&self, obj: Arc<crd::Authentik>) -> Result<Action> {
+    async fn cleanup_parts(&self, obj: &crd::Authentik) -> Result<()> {
+        // Cleanup all parts.
+        rotation::cleanup(obj, self.client.clone()).await?;
+        secret::cleanup(obj, self.client.clone()).await?;
+        servicegroup::cleanup(obj, self.client.clone()).await?;
+        serviceaccount::cleanup(obj, self.client.clone()).await?;
+        ingress::cleanup(obj, self.client.clone()).await?;
+        service::cleanup(obj, self.client.clone()).await?;
+        disruptionbudget::cleanup(obj, self.client.clone()).await?;
+        autoscaler::cleanup(obj, self.client.clone()).await?;
+        deployment::cleanup(obj, self.client.clone()).await?;
+        clusteraccount::cleanup(obj, self.client.clone()).await?;
+        secretkey::cleanup(obj, self.client.clone()).await?;
+        bootstrap::cleanup(obj, self.client.clone()).await?;
+
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl Reconciler for Controller {
+    type Kind = crd::Authentik;
+
+    const FINALIZER: &'static str = "authentik/ak.dany.dev";
+
+    async fn reconcile(&self, obj: Arc<crd::Authentik>) -> Result<Action> {
         info!("Starting reconcilidation of Authentik.");
-        let name = obj.name_any();
         let ns = obj
             .namespace()
             .ok_or(anyhow!("Missing namespace `{}`.", obj.name_any()))?;
         let servers: Api<crd::Authentik> = Api::namespaced(self.client.clone(), &ns);
 
-        // Generate any default values.
-        let mut obj = obj.as_ref().clone();
-        let changed = self.autofill(&mut obj, &servers, name.as_str()).await?;
-
-        if changed {
-            // CRD updated, requeue so the changes are actually reflected.
-            return Ok(Action::requeue(Duration::from_secs(1)));
+        // Reconcile all parts, keeping track of the progress in the status.
+        let mut status = obj.status.clone().unwrap_or_default();
+        status.observed_generation = obj.metadata.generation;
+        let result = self.reconcile_parts(&obj, &mut status).await;
+
+        if let Err(e) = &result {
+            self.recorder
+                .warn(obj.as_ref(), "ReconcileFailed", "Reconcile", e.to_string())
+                .await;
+        } else {
+            self.recorder.resolved(obj.as_ref());
         }
 
-        // Reconcile all parts.
-        clusteraccount::reconcile(&obj, self.client.clone()).await?;
-        deployment::reconcile(&obj, self.client.clone()).await?;
-        service::reconcile(&obj, self.client.clone()).await?;
-        ingress::reconcile(&obj, self.client.clone()).await?;
-        serviceaccount::reconcile(&obj, self.client.clone()).await?;
-        servicegroup::reconcile(&obj, self.client.clone()).await?;
-        secret::reconcile(&obj, self.client.clone()).await?;
-
-        info!("Reconcilidation of Authentik finished successfully, re-queued for 30 minutes.");
-        Ok(Action::requeue(Duration::from_secs(30 * 60)))
-    }
-
-    pub async fn cleanup(&self, obj: Arc<crd::Authentik>) -> Result<Action> {
-        // Cleanup all parts.
-        secret::cleanup(obj.as_ref(), self.client.clone()).await?;
-        servicegroup::cleanup(obj.as_ref(), self.client.clone()).await?;
-        serviceaccount::cleanup(obj.as_ref(), self.client.clone()).await?;
-        ingress::cleanup(obj.as_ref(), self.client.clone()).await?;
-        service::cleanup(obj.as_ref(), self.client.clone()).await?;
-        deployment::cleanup(obj.as_ref(), self.client.clone()).await?;
-        clusteraccount::cleanup(obj.as_ref(), self.client.clone()).await?;
-
-        Ok(Action::await_change())
-    }
-
-    async fn autofill(
-        &self,
-        obj: &mut crd::Authentik,
-        api: &Api<crd::Authentik>,
-        name: &str,
-    ) -> Result<bool> {
-        let mut values = Map::new();
-
-        if obj.spec.secret_key.is_none() {
-            // Create the secret if it does not yet exist
-            let secret: String = thread_rng()
-                .sample_iter(&Alphanumeric)
-                .take(128)
-                .map(char::from)
-                .collect();
-
-            values.insert("secretKey".to_string(), json!(secret));
+        if let Ok(None) = result {
+            status.set(READY, true, "ReconcileSucceeded", "");
         }
 
-        if values.is_empty() {
-            return Ok(false);
+        match status::update(&servers, obj.as_ref(), obj.status.as_ref(), &status, result).await? {
+            Some(action) => Ok(action),
+            None => {
+                let interval = requeue::resync_interval(obj.as_ref());
+                info!(
+                    "Reconcilidation of Authentik finished successfully, re-queued in {} seconds.",
+                    interval.as_secs()
+                );
+                Ok(Action::requeue(interval))
+            }
         }
+    }
 
-        let pp = PatchParams::apply("authentik.ak-operator").force();
-        let patched_secret = Patch::Apply(json!({
-            "apiVersion": "ak.dany.dev/v1",
-            "kind": "Authentik",
-            "spec": values
-        }));
+    async fn cleanup(&self, obj: Arc<crd::Authentik>) -> Result<Action> {
+        if let Err(e) = self.cleanup_parts(obj.as_ref()).await {
+            self.recorder
+                .warn(obj.as_ref(), "CleanupFailed", "Cleanup", e.to_string())
+                .await;
+            return Err(e);
+        }
 
-        api.patch(&name, &pp, &patched_secret).await?;
-        Ok(true)
+        self.recorder.resolved(obj.as_ref());
+        Ok(Action::await_change())
     }
 }
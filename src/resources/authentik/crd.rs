@@ -1,7 +1,24 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use chrono::{DateTime, Utc};
+use k8s_openapi::{
+    api::core::v1::{
+        Affinity, Container, EnvFromSource, EnvVar, LocalObjectReference, PodSecurityContext,
+        ResourceRequirements, SecurityContext, Toleration, TopologySpreadConstraint, Volume,
+        VolumeMount,
+    },
+    apimachinery::pkg::util::intstr::IntOrString,
+};
 use kube::CustomResource;
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
+use crate::resources::status::{set_condition, Condition, READY};
+
+pub static CONDITION_DEPLOYMENTS_AVAILABLE: &str = "DeploymentsAvailable";
+pub static CONDITION_API_TOKEN_VALID: &str = "ApiTokenValid";
+pub static CONDITION_SERVICE_GROUP_READY: &str = "ServiceGroupReady";
+
 #[derive(CustomResource, Deserialize, Serialize, Clone, Debug, JsonSchema)]
 #[serde(rename_all = "camelCase")]
 #[kube(
@@ -10,10 +27,12 @@ use serde::{Deserialize, Serialize};
     version = "v1",
     plural = "authentik",
     shortname = "ak",
+    status = "AuthentikStatus",
     namespaced
 )]
 pub struct AuthentikSpec {
     pub secret_key: Option<String>,
+    pub secret_key_ref: Option<AuthentikSecretKeyRef>,
     #[serde(default = "default_log_level")]
     pub log_level: String,
     #[serde(default = "default_avatars")]
@@ -21,11 +40,131 @@ pub struct AuthentikSpec {
     #[serde(default = "default_image")]
     pub image: AuthentikImage,
     #[serde(default)]
+    pub server: AuthentikComponent,
+    #[serde(default)]
+    pub worker: AuthentikComponent,
+    #[serde(default)]
     pub footer_links: Vec<AuthentikFooterLink>,
     pub ingress: Option<AuthentikIngress>,
     pub postgres: AuthentikPostgres,
     pub redis: AuthentikRedis,
     pub smtp: Option<AuthentikSmtp>,
+    #[serde(default)]
+    pub api: AuthentikApi,
+    #[validate(range(min = 1))]
+    pub token_rotation_hours: Option<u32>,
+    #[serde(default)]
+    pub extra_env: Vec<EnvVar>,
+    #[serde(default)]
+    pub env_from: Vec<EnvFromSource>,
+}
+
+impl AuthentikSpec {
+    /// Names of the secrets referenced from the spec, which the pods depend on.
+    pub fn referenced_secrets(&self) -> BTreeSet<String> {
+        let mut names = BTreeSet::new();
+        names.extend(self.secret_key_ref.iter().map(|r| r.name.clone()));
+        names.extend(self.postgres.password_secret.clone());
+        names.extend(self.redis.password_ref.iter().map(|r| r.name.clone()));
+        names.extend(
+            [
+                &self.postgres.ssl_root_cert_ref,
+                &self.postgres.ssl_cert_ref,
+                &self.postgres.ssl_key_ref,
+                &self.redis.tls_ca_cert_ref,
+            ]
+            .into_iter()
+            .flatten()
+            .map(|r| r.name.clone()),
+        );
+        names.extend(
+            self.smtp
+                .iter()
+                .filter_map(|smtp| smtp.password_ref.as_ref())
+                .map(|r| r.name.clone()),
+        );
+        names.extend(self.extra_env.iter().filter_map(|env| {
+            env.value_from
+                .as_ref()?
+                .secret_key_ref
+                .as_ref()?
+                .name
+                .clone()
+        }));
+        names.extend(
+            self.env_from
+                .iter()
+                .filter_map(|env| env.secret_ref.as_ref()?.name.clone()),
+        );
+        names
+    }
+
+    /// Names of the config maps referenced from the spec, which the pods depend on.
+    pub fn referenced_config_maps(&self) -> BTreeSet<String> {
+        let mut names = BTreeSet::new();
+        names.extend(self.extra_env.iter().filter_map(|env| {
+            env.value_from
+                .as_ref()?
+                .config_map_key_ref
+                .as_ref()?
+                .name
+                .clone()
+        }));
+        names.extend(
+            self.env_from
+                .iter()
+                .filter_map(|env| env.config_map_ref.as_ref()?.name.clone()),
+        );
+        names
+    }
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct AuthentikSecretKeyRef {
+    #[validate(length(min = 1))]
+    pub name: String,
+    #[validate(length(min = 1))]
+    pub key: String,
+}
+
+/// How the operator connects to the API of the instance.
+#[derive(Deserialize, Serialize, Clone, Debug, Default, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct AuthentikApi {
+    #[serde(default)]
+    pub scheme: AuthentikApiScheme,
+    #[serde(default)]
+    #[schemars(regex(pattern = r"^(/.*)?$"))]
+    pub path_prefix: String,
+    pub ca: Option<AuthentikCaBundle>,
+    pub client_certificate: Option<AuthentikClientCertificate>,
+}
+
+#[derive(Deserialize, Serialize, Clone, Copy, Debug, Default, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum AuthentikApiScheme {
+    #[default]
+    Http,
+    Https,
+}
+
+/// A bundle of PEM encoded CA certificates, within a secret or a config map.
+#[derive(Deserialize, Serialize, Clone, Debug, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct AuthentikCaBundle {
+    pub secret_name: Option<String>,
+    pub config_map_name: Option<String>,
+    #[serde(default = "default_ca_key")]
+    pub key: String,
+}
+
+/// A client certificate, within a secret of the type `kubernetes.io/tls`.
+#[derive(Deserialize, Serialize, Clone, Debug, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct AuthentikClientCertificate {
+    #[validate(length(min = 1))]
+    pub secret_name: String,
 }
 
 #[derive(Deserialize, Serialize, Clone, Debug, JsonSchema)]
@@ -39,6 +178,76 @@ pub struct AuthentikImage {
     pub pull_policy: String,
 }
 
+#[derive(Deserialize, Serialize, Clone, Debug, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct AuthentikComponent {
+    #[serde(default = "default_replicas")]
+    pub replicas: i32,
+    pub autoscaling: Option<AuthentikAutoscaling>,
+    pub pod_disruption_budget: Option<AuthentikDisruptionBudget>,
+    #[serde(default)]
+    pub pod_template: AuthentikPodTemplate,
+}
+
+impl Default for AuthentikComponent {
+    fn default() -> Self {
+        Self {
+            replicas: default_replicas(),
+            autoscaling: None,
+            pod_disruption_budget: None,
+            pod_template: AuthentikPodTemplate::default(),
+        }
+    }
+}
+
+/// Overrides merged into the generated pod spec of a component.
+#[derive(Deserialize, Serialize, Clone, Debug, Default, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct AuthentikPodTemplate {
+    #[serde(default)]
+    pub labels: BTreeMap<String, String>,
+    #[serde(default)]
+    pub annotations: BTreeMap<String, String>,
+    pub resources: Option<ResourceRequirements>,
+    #[serde(default)]
+    pub node_selector: BTreeMap<String, String>,
+    #[serde(default)]
+    pub tolerations: Vec<Toleration>,
+    pub affinity: Option<Affinity>,
+    #[serde(default)]
+    pub topology_spread_constraints: Vec<TopologySpreadConstraint>,
+    pub priority_class_name: Option<String>,
+    pub security_context: Option<PodSecurityContext>,
+    pub container_security_context: Option<SecurityContext>,
+    #[serde(default)]
+    pub image_pull_secrets: Vec<LocalObjectReference>,
+    #[serde(default)]
+    pub volumes: Vec<Volume>,
+    #[serde(default)]
+    pub volume_mounts: Vec<VolumeMount>,
+    #[serde(default)]
+    pub init_containers: Vec<Container>,
+    #[serde(default)]
+    pub sidecars: Vec<Container>,
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct AuthentikAutoscaling {
+    #[serde(default = "default_replicas")]
+    pub min_replicas: i32,
+    pub max_replicas: i32,
+    #[serde(default = "default_autoscaling_cpu")]
+    pub target_cpu_utilization_percentage: i32,
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct AuthentikDisruptionBudget {
+    pub min_available: Option<IntOrString>,
+    pub max_unavailable: Option<IntOrString>,
+}
+
 #[derive(Deserialize, Serialize, Clone, Debug, JsonSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct AuthentikFooterLink {
@@ -90,6 +299,10 @@ pub struct AuthentikPostgres {
     pub password: String,
     pub password_secret: Option<String>,
     pub password_secret_key: Option<String>,
+    pub ssl_mode: Option<String>,
+    pub ssl_root_cert_ref: Option<AuthentikSecretKeyRef>,
+    pub ssl_cert_ref: Option<AuthentikSecretKeyRef>,
+    pub ssl_key_ref: Option<AuthentikSecretKeyRef>,
 }
 
 #[derive(Deserialize, Serialize, Clone, Debug, JsonSchema)]
@@ -99,6 +312,11 @@ pub struct AuthentikRedis {
     #[serde(default = "default_redis_port")]
     pub port: u16,
     pub password: Option<String>,
+    pub password_ref: Option<AuthentikSecretKeyRef>,
+    #[serde(default)]
+    pub tls: bool,
+    pub tls_reqs: Option<String>,
+    pub tls_ca_cert_ref: Option<AuthentikSecretKeyRef>,
 }
 
 #[derive(Deserialize, Serialize, Clone, Debug, JsonSchema)]
@@ -112,6 +330,7 @@ pub struct AuthentikSmtp {
     pub username: String,
     #[serde(default)]
     pub password: String,
+    pub password_ref: Option<AuthentikSecretKeyRef>,
     #[serde(default = "default_smtp_tls")]
     pub use_tls: bool,
     #[serde(default = "default_smtp_ssl")]
@@ -120,6 +339,42 @@ pub struct AuthentikSmtp {
     pub timeout: u16,
 }
 
+#[derive(Deserialize, Serialize, Clone, Debug, Default, PartialEq, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct AuthentikStatus {
+    #[serde(default)]
+    pub conditions: Vec<Condition>,
+    pub observed_generation: Option<i64>,
+    pub image_tag: Option<String>,
+    pub token_rotated_at: Option<DateTime<Utc>>,
+    // Identifier of the token replaced by the last rotation, until it's revoked.
+    pub revoke_token: Option<String>,
+}
+
+impl AuthentikStatus {
+    pub fn set(&mut self, type_: &str, status: bool, reason: &str, message: &str) {
+        set_condition(
+            &mut self.conditions,
+            type_,
+            status,
+            reason,
+            message,
+            self.observed_generation,
+        );
+    }
+
+    /// Marks the given condition, and the ready condition, as failed.
+    pub fn fail(&mut self, type_: &str, reason: &str, error: anyhow::Error) -> anyhow::Error {
+        let message = error.to_string();
+        self.set(type_, false, reason, &message);
+        if type_ != READY {
+            self.set(READY, false, reason, &message);
+        }
+
+        error
+    }
+}
+
 // -- Default value functions from here on.
 fn default_log_level() -> String {
     "info".to_string()
@@ -149,6 +404,18 @@ fn default_image_pullpolicy() -> String {
     "IfNotPresent".to_string()
 }
 
+fn default_ca_key() -> String {
+    "ca.crt".to_string()
+}
+
+fn default_replicas() -> i32 {
+    1
+}
+
+fn default_autoscaling_cpu() -> i32 {
+    80
+}
+
 fn default_ingress_path_type() -> String {
     "ImplementationSpecific".to_string()
 }
@@ -180,3 +447,94 @@ fn default_smtp_ssl() -> bool {
 fn default_smtp_timeout() -> u16 {
     10
 }
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeSet;
+
+    use serde_json::json;
+
+    use super::AuthentikSpec;
+
+    fn spec(extra: serde_json::Value) -> AuthentikSpec {
+        let mut spec = json!({
+            "postgres": { "host": "postgres", "database": "authentik", "username": "authentik" },
+            "redis": { "host": "redis" }
+        });
+        for (key, value) in extra.as_object().unwrap() {
+            spec[key] = value.clone();
+        }
+
+        serde_json::from_value(spec).unwrap()
+    }
+
+    fn names(names: &[&str]) -> BTreeSet<String> {
+        names.iter().map(|name| name.to_string()).collect()
+    }
+
+    #[test]
+    fn references_nothing_by_default() {
+        let spec = spec(json!({}));
+
+        assert!(spec.referenced_secrets().is_empty());
+        assert!(spec.referenced_config_maps().is_empty());
+    }
+
+    #[test]
+    fn collects_the_referenced_secrets() {
+        let spec = spec(json!({
+            "secretKeyRef": { "name": "secret-key", "key": "key" },
+            "postgres": {
+                "host": "postgres",
+                "database": "authentik",
+                "username": "authentik",
+                "passwordSecret": "postgres",
+                "sslRootCertRef": { "name": "postgres-ca", "key": "ca.crt" }
+            },
+            "redis": {
+                "host": "redis",
+                "passwordRef": { "name": "redis", "key": "password" }
+            },
+            "smtp": {
+                "host": "smtp",
+                "from": "authentik@example.com",
+                "passwordRef": { "name": "smtp", "key": "password" }
+            },
+            "extraEnv": [
+                { "name": "A", "valueFrom": { "secretKeyRef": { "name": "env", "key": "a" } } },
+                { "name": "B", "valueFrom": { "configMapKeyRef": { "name": "config", "key": "b" } } }
+            ],
+            "envFrom": [{ "secretRef": { "name": "env-from" } }]
+        }));
+
+        assert_eq!(
+            spec.referenced_secrets(),
+            names(&[
+                "env",
+                "env-from",
+                "postgres",
+                "postgres-ca",
+                "redis",
+                "secret-key",
+                "smtp"
+            ])
+        );
+    }
+
+    #[test]
+    fn collects_the_referenced_config_maps() {
+        let spec = spec(json!({
+            "extraEnv": [
+                { "name": "A", "valueFrom": { "configMapKeyRef": { "name": "config", "key": "a" } } },
+                { "name": "B", "value": "b" }
+            ],
+            "envFrom": [{ "configMapRef": { "name": "config-from" } }]
+        }));
+
+        assert_eq!(
+            spec.referenced_config_maps(),
+            names(&["config", "config-from"])
+        );
+        assert!(spec.referenced_secrets().is_empty());
+    }
+}
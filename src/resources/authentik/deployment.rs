@@ -1,18 +1,34 @@
 use anyhow::{anyhow, Result};
 use k8s_openapi::api::{
     apps::v1::Deployment,
-    core::v1::{EnvVar, EnvVarSource, SecretKeySelector},
+    core::v1::{ConfigMap, EnvVar, EnvVarSource, Secret, SecretKeySelector},
 };
 use kube::{
     api::{Patch, PatchParams},
     Api, Client, ResourceExt,
 };
 use serde_json::{json, Value};
-
-use crate::akapi::auth::TEMP_AUTH_TOKEN;
+use sha2::{Digest, Sha256};
 
 use super::{crd, labels};
 
+static CHECKSUM_ANNOTATION: &str = "ak.dany.dev/config-checksum";
+static CERTS_PATH: &str = "/certs";
+
+/// A certificate or key from a secret, which is mounted into the pods.
+struct CertFile<'a> {
+    volume: &'static str,
+    file: &'static str,
+    mode: i32,
+    secret_ref: &'a crd::AuthentikSecretKeyRef,
+}
+
+impl CertFile<'_> {
+    fn path(&self) -> String {
+        format!("{}/{}/{}", CERTS_PATH, self.volume, self.file)
+    }
+}
+
 pub async fn reconcile(obj: &crd::Authentik, client: Client) -> Result<()> {
     let instance = obj
         .metadata
@@ -23,12 +39,25 @@ pub async fn reconcile(obj: &crd::Authentik, client: Client) -> Result<()> {
         .namespace()
         .ok_or(anyhow!("Missing namespace `{}`.", instance.clone()))?;
 
+    validate_extra_env(&instance, obj)?;
+
+    // The bootstrap token is only needed until the operator has created its own token.
+    // This doesn't depend on the API, so a rejected or unreachable server doesn't roll the pods.
+    let secrets: Api<Secret> = Api::namespaced(client.clone(), &ns);
+    let bootstrap = secrets
+        .get_opt(&format!("ak-{}-api-operatortoken", instance))
+        .await?
+        .is_none();
+
+    // Roll the pods when any of the referenced secrets or config maps change.
+    let checksum = checksum(obj, client.clone()).await?;
+
     // Create the server deployment.
     let api: Api<Deployment> = Api::namespaced(client.clone(), &ns);
     api.patch(
         &format!("authentik-{}-server", instance),
         &PatchParams::apply("authentik.ak-operator").force(),
-        &Patch::Apply(build_server(instance.clone(), obj)),
+        &Patch::Apply(build_server(instance.clone(), obj, bootstrap, &checksum)),
     )
     .await?;
 
@@ -37,7 +66,7 @@ pub async fn reconcile(obj: &crd::Authentik, client: Client) -> Result<()> {
     api.patch(
         &format!("authentik-{}-worker", instance),
         &PatchParams::apply("authentik.ak-operator").force(),
-        &Patch::Apply(build_worker(instance.clone(), obj)),
+        &Patch::Apply(build_worker(instance.clone(), obj, bootstrap, &checksum)),
     )
     .await?;
 
@@ -48,8 +77,96 @@ pub async fn cleanup(_obj: &crd::Authentik, _client: Client) -> Result<()> {
     Ok(())
 }
 
-fn build_server(name: String, obj: &crd::Authentik) -> Value {
-    json!({
+/// Check if both deployments have available pods.
+/// Also returns the image tag of the server, if it's fully rolled out.
+pub async fn availability(obj: &crd::Authentik, client: Client) -> Result<(bool, Option<String>)> {
+    let instance = obj.name_any();
+    let ns = obj
+        .namespace()
+        .ok_or(anyhow!("Missing namespace `{}`.", instance.clone()))?;
+    let api: Api<Deployment> = Api::namespaced(client, &ns);
+
+    let server = api
+        .get_opt(&format!("authentik-{}-server", instance))
+        .await?;
+    let worker = api
+        .get_opt(&format!("authentik-{}-worker", instance))
+        .await?;
+
+    let available = [&server, &worker].iter().all(|deploy| {
+        deploy
+            .as_ref()
+            .and_then(|d| d.status.as_ref())
+            .and_then(|s| s.available_replicas)
+            .unwrap_or(0)
+            > 0
+    });
+
+    let tag = server.and_then(|deploy| {
+        let status = deploy.status.as_ref()?;
+        let rolled_out = status.observed_generation == deploy.metadata.generation
+            && status.updated_replicas == status.replicas
+            && status.available_replicas.unwrap_or(0) > 0;
+        if !rolled_out {
+            return None;
+        }
+
+        deploy
+            .spec?
+            .template
+            .metadata?
+            .labels?
+            .remove("app.kubernetes.io/version")
+    });
+
+    Ok((available, tag))
+}
+
+/// Calculate a checksum over the contents of all secrets and config maps referenced by the spec.
+async fn checksum(obj: &crd::Authentik, client: Client) -> Result<String> {
+    let ns = obj
+        .namespace()
+        .ok_or(anyhow!("Missing namespace `{}`.", obj.name_any()))?;
+    let secrets: Api<Secret> = Api::namespaced(client.clone(), &ns);
+    let config_maps: Api<ConfigMap> = Api::namespaced(client, &ns);
+
+    // Every field is prefixed with its length, so no two distinct inputs hash the same.
+    let mut hasher = Sha256::new();
+    for name in obj.spec.referenced_secrets() {
+        hash_field(&mut hasher, b"secret");
+        hash_field(&mut hasher, name.as_bytes());
+        if let Some(secret) = secrets.get_opt(&name).await? {
+            for (key, value) in secret.data.unwrap_or_default() {
+                hash_field(&mut hasher, key.as_bytes());
+                hash_field(&mut hasher, &value.0);
+            }
+        }
+    }
+    for name in obj.spec.referenced_config_maps() {
+        hash_field(&mut hasher, b"configmap");
+        hash_field(&mut hasher, name.as_bytes());
+        if let Some(config_map) = config_maps.get_opt(&name).await? {
+            for (key, value) in config_map.data.unwrap_or_default() {
+                hash_field(&mut hasher, key.as_bytes());
+                hash_field(&mut hasher, value.as_bytes());
+            }
+            for (key, value) in config_map.binary_data.unwrap_or_default() {
+                hash_field(&mut hasher, key.as_bytes());
+                hash_field(&mut hasher, &value.0);
+            }
+        }
+    }
+
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+fn hash_field(hasher: &mut Sha256, field: &[u8]) {
+    hasher.update((field.len() as u64).to_be_bytes());
+    hasher.update(field);
+}
+
+fn build_server(name: String, obj: &crd::Authentik, bootstrap: bool, checksum: &str) -> Value {
+    let mut deploy = json!({
         "apiVersion": "apps/v1",
         "kind": "Deployment",
         "metadata": {
@@ -64,26 +181,35 @@ fn build_server(name: String, obj: &crd::Authentik) -> Value {
             }]
         },
         "spec": {
-            "replicas": 1,
+            "replicas": replicas(&obj.spec.server),
             "selector": {
                 "matchLabels": labels::get_matching_labels(name.clone(), "server".to_string())
             },
             "template": {
                 "metadata": {
                     "labels": labels::get_labels(name.clone(), obj.spec.image.tag.to_string(), "server".to_string()),
+                    "annotations": {
+                        CHECKSUM_ANNOTATION: checksum
+                    }
                 },
                 "spec": {
                     "serviceAccountName": format!("ak-{}", name),
                     "enableServiceLinks": true,
+                    "volumes": build_cert_volumes(&obj.spec),
                     "containers": [{
                         "name": format!("authentik-{}-server", name),
                         "image": format!("{}:{}", obj.spec.image.repository, obj.spec.image.tag),
                         "imagePullPolicy": obj.spec.image.pull_policy,
                         "args": ["server"],
+                        "volumeMounts": build_cert_mounts(&obj.spec),
                         "ports": [{
                             "name": "http",
                             "containerPort": 9000,
                             "protocol": "TCP"
+                        }, {
+                            "name": "https",
+                            "containerPort": 9443,
+                            "protocol": "TCP"
                         }],
                         "startupProbe": {
                             "failureThreshold": 30,
@@ -109,16 +235,23 @@ fn build_server(name: String, obj: &crd::Authentik) -> Value {
                                 "port": "http"
                             }
                         },
-                        "env": build_env(&obj.spec)
+                        "env": build_env(&name, &obj.spec, bootstrap),
+                        "envFrom": obj.spec.env_from
                     }]
                 }
             }
         }
-    })
+    });
+
+    merge_pod_template(
+        &mut deploy["spec"]["template"],
+        &obj.spec.server.pod_template,
+    );
+    deploy
 }
 
-fn build_worker(name: String, obj: &crd::Authentik) -> Value {
-    json!({
+fn build_worker(name: String, obj: &crd::Authentik, bootstrap: bool, checksum: &str) -> Value {
+    let mut deploy = json!({
         "apiVersion": "apps/v1",
         "kind": "Deployment",
         "metadata": {
@@ -133,47 +266,116 @@ fn build_worker(name: String, obj: &crd::Authentik) -> Value {
             }]
         },
         "spec": {
-            "replicas": 1,
+            "replicas": replicas(&obj.spec.worker),
             "selector": {
                 "matchLabels": labels::get_matching_labels(name.clone(), "worker".to_string())
             },
             "template": {
                 "metadata": {
                     "labels": labels::get_labels(name.clone(), obj.spec.image.tag.to_string(), "worker".to_string()),
+                    "annotations": {
+                        CHECKSUM_ANNOTATION: checksum
+                    }
                 },
                 "spec": {
                     "serviceAccountName": format!("ak-{}", name),
                     "enableServiceLinks": true,
+                    "volumes": build_cert_volumes(&obj.spec),
                     "containers": [{
                         "name": format!("authentik-{}-worker", name),
                         "image": format!("{}:{}", obj.spec.image.repository, obj.spec.image.tag),
                         "imagePullPolicy": obj.spec.image.pull_policy,
                         "args": ["worker"],
-                        "env": build_env(&obj.spec)
+                        "volumeMounts": build_cert_mounts(&obj.spec),
+                        "env": build_env(&name, &obj.spec, bootstrap),
+                        "envFrom": obj.spec.env_from
                     }]
                 }
             }
         }
-    })
+    });
+
+    merge_pod_template(
+        &mut deploy["spec"]["template"],
+        &obj.spec.worker.pod_template,
+    );
+    deploy
 }
 
-fn build_env(obj: &crd::AuthentikSpec) -> Vec<EnvVar> {
+/// Merge the user provided pod template overrides into the generated pod template.
+/// Labels and annotations set by the operator take precedence.
+fn merge_pod_template(template: &mut Value, overrides: &crd::AuthentikPodTemplate) {
+    for (key, value) in &overrides.labels {
+        if template["metadata"]["labels"].get(key).is_none() {
+            template["metadata"]["labels"][key] = json!(value);
+        }
+    }
+    for (key, value) in &overrides.annotations {
+        if template["metadata"]["annotations"].get(key).is_none() {
+            template["metadata"]["annotations"][key] = json!(value);
+        }
+    }
+
+    let spec = &mut template["spec"];
+    if !overrides.node_selector.is_empty() {
+        spec["nodeSelector"] = json!(overrides.node_selector);
+    }
+    if !overrides.tolerations.is_empty() {
+        spec["tolerations"] = json!(overrides.tolerations);
+    }
+    if let Some(affinity) = &overrides.affinity {
+        spec["affinity"] = json!(affinity);
+    }
+    if !overrides.topology_spread_constraints.is_empty() {
+        spec["topologySpreadConstraints"] = json!(overrides.topology_spread_constraints);
+    }
+    if let Some(priority_class_name) = &overrides.priority_class_name {
+        spec["priorityClassName"] = json!(priority_class_name);
+    }
+    if let Some(security_context) = &overrides.security_context {
+        spec["securityContext"] = json!(security_context);
+    }
+    if !overrides.image_pull_secrets.is_empty() {
+        spec["imagePullSecrets"] = json!(overrides.image_pull_secrets);
+    }
+    if let Some(volumes) = spec["volumes"].as_array_mut() {
+        volumes.extend(overrides.volumes.iter().map(|volume| json!(volume)));
+    }
+    if !overrides.init_containers.is_empty() {
+        spec["initContainers"] = json!(overrides.init_containers);
+    }
+
+    let container = &mut spec["containers"][0];
+    if let Some(resources) = &overrides.resources {
+        container["resources"] = json!(resources);
+    }
+    if let Some(security_context) = &overrides.container_security_context {
+        container["securityContext"] = json!(security_context);
+    }
+    if let Some(mounts) = container["volumeMounts"].as_array_mut() {
+        mounts.extend(overrides.volume_mounts.iter().map(|mount| json!(mount)));
+    }
+
+    if let Some(containers) = spec["containers"].as_array_mut() {
+        containers.extend(overrides.sidecars.iter().map(|sidecar| json!(sidecar)));
+    }
+}
+
+fn replicas(component: &crd::AuthentikComponent) -> Option<i32> {
+    // Leave the replicas to the autoscaler if it's enabled.
+    match component.autoscaling {
+        Some(_) => None,
+        None => Some(component.replicas),
+    }
+}
+
+fn build_env(name: &str, obj: &crd::AuthentikSpec, bootstrap: bool) -> Vec<EnvVar> {
     let mut env = vec![
         EnvVar {
             name: "AUTHENTIK_LOG_LEVEL".to_string(),
             value: Some(obj.log_level.clone()),
             value_from: None,
         },
-        EnvVar {
-            name: "AUTHENTIK_SECRET_KEY".to_string(),
-            value: obj.secret_key.clone(),
-            value_from: None,
-        },
-        EnvVar {
-            name: "AUTHENTIK_BOOTSTRAP_TOKEN".to_string(),
-            value: Some(TEMP_AUTH_TOKEN.to_string()),
-            value_from: None,
-        },
         EnvVar {
             name: "AUTHENTIK_FOOTER_LINKS".to_string(),
             value: Some(serde_json::to_string(&obj.footer_links).expect("Invalid footer")),
@@ -221,26 +423,34 @@ fn build_env(obj: &crd::AuthentikSpec) -> Vec<EnvVar> {
         },
     ];
 
+    if let Some(secret_ref) = obj.secret_key_ref.as_ref() {
+        env.push(build_secret_env("AUTHENTIK_SECRET_KEY", secret_ref));
+    } else if let Some(secret_key) = obj.secret_key.as_ref() {
+        env.push(EnvVar {
+            name: "AUTHENTIK_SECRET_KEY".to_string(),
+            value: Some(secret_key.clone()),
+            value_from: None,
+        });
+    } else {
+        env.push(build_secret_env(
+            "AUTHENTIK_SECRET_KEY",
+            &crd::AuthentikSecretKeyRef {
+                name: format!("ak-{}-secretkey", name),
+                key: "secretKey".to_string(),
+            },
+        ));
+    }
+
     if let Some((secret, key)) = obj
         .postgres
         .password_secret
         .clone()
-        .zip(obj.postgres.password_secret_key.as_ref())
+        .zip(obj.postgres.password_secret_key.clone())
     {
-        env.push(EnvVar {
-            name: "AUTHENTIK_POSTGRESQL__PASSWORD".to_string(),
-            value: None,
-            value_from: Some(EnvVarSource {
-                config_map_key_ref: None,
-                field_ref: None,
-                resource_field_ref: None,
-                secret_key_ref: Some(SecretKeySelector {
-                    key: key.clone(),
-                    name: Some(secret),
-                    optional: Some(false),
-                }),
-            }),
-        });
+        env.push(build_secret_env(
+            "AUTHENTIK_POSTGRESQL__PASSWORD",
+            &crd::AuthentikSecretKeyRef { name: secret, key },
+        ));
     } else {
         env.push(EnvVar {
             name: "AUTHENTIK_POSTGRESQL__PASSWORD".to_string(),
@@ -249,7 +459,9 @@ fn build_env(obj: &crd::AuthentikSpec) -> Vec<EnvVar> {
         });
     }
 
-    if let Some(password) = obj.redis.password.as_ref() {
+    if let Some(secret_ref) = obj.redis.password_ref.as_ref() {
+        env.push(build_secret_env("AUTHENTIK_REDIS__PASSWORD", secret_ref));
+    } else if let Some(password) = obj.redis.password.as_ref() {
         env.push(EnvVar {
             name: "AUTHENTIK_REDIS__PASSWORD".to_string(),
             value: Some(password.clone()),
@@ -257,17 +469,177 @@ fn build_env(obj: &crd::AuthentikSpec) -> Vec<EnvVar> {
         });
     }
 
+    if bootstrap {
+        env.push(build_secret_env(
+            "AUTHENTIK_BOOTSTRAP_TOKEN",
+            &crd::AuthentikSecretKeyRef {
+                name: format!("ak-{}-bootstrap", name),
+                key: "token".to_string(),
+            },
+        ));
+    }
+
+    env.extend(build_env_tls(obj));
     env.extend(build_env_smtp(obj.smtp.as_ref()));
+    env.extend(obj.extra_env.iter().cloned());
 
     env
 }
 
+/// Make sure the extra environment variables don't override the ones managed by the operator.
+fn validate_extra_env(name: &str, obj: &crd::Authentik) -> Result<()> {
+    let spec = crd::AuthentikSpec {
+        extra_env: vec![],
+        ..obj.spec.clone()
+    };
+    let managed = build_env(name, &spec, true);
+
+    for var in &obj.spec.extra_env {
+        if managed.iter().any(|m| m.name == var.name) {
+            return Err(anyhow!(
+                "The environment variable `{}` is managed by the operator and can't be set in extraEnv.",
+                var.name
+            ));
+        }
+    }
+
+    Ok(())
+}
+
+fn build_env_tls(obj: &crd::AuthentikSpec) -> Vec<EnvVar> {
+    let mut env = vec![];
+
+    if let Some(ssl_mode) = obj.postgres.ssl_mode.as_ref() {
+        env.push(EnvVar {
+            name: "AUTHENTIK_POSTGRESQL__SSLMODE".to_string(),
+            value: Some(ssl_mode.clone()),
+            value_from: None,
+        });
+    }
+
+    if obj.redis.tls {
+        env.push(EnvVar {
+            name: "AUTHENTIK_REDIS__TLS".to_string(),
+            value: Some("true".to_string()),
+            value_from: None,
+        });
+    }
+    if let Some(tls_reqs) = obj.redis.tls_reqs.as_ref() {
+        env.push(EnvVar {
+            name: "AUTHENTIK_REDIS__TLS_REQS".to_string(),
+            value: Some(tls_reqs.clone()),
+            value_from: None,
+        });
+    }
+
+    for (name, file) in cert_files(obj) {
+        env.push(EnvVar {
+            name: name.to_string(),
+            value: Some(file.path()),
+            value_from: None,
+        });
+    }
+
+    env
+}
+
+/// All certificate files which should be mounted, with the variable pointing to them.
+fn cert_files(obj: &crd::AuthentikSpec) -> Vec<(&'static str, CertFile<'_>)> {
+    let files = [
+        (
+            "AUTHENTIK_POSTGRESQL__SSLROOTCERT",
+            "postgres-sslrootcert",
+            "root.crt",
+            0o444,
+            &obj.postgres.ssl_root_cert_ref,
+        ),
+        (
+            "AUTHENTIK_POSTGRESQL__SSLCERT",
+            "postgres-sslcert",
+            "client.crt",
+            0o444,
+            &obj.postgres.ssl_cert_ref,
+        ),
+        (
+            // Postgres refuses keys which are readable by everyone.
+            "AUTHENTIK_POSTGRESQL__SSLKEY",
+            "postgres-sslkey",
+            "client.key",
+            0o440,
+            &obj.postgres.ssl_key_ref,
+        ),
+        (
+            "AUTHENTIK_REDIS__TLS_CA_CERT",
+            "redis-tlscacert",
+            "ca.crt",
+            0o444,
+            &obj.redis.tls_ca_cert_ref,
+        ),
+    ];
+
+    files
+        .into_iter()
+        .filter_map(|(name, volume, file, mode, secret_ref)| {
+            Some((
+                name,
+                CertFile {
+                    volume,
+                    file,
+                    mode,
+                    secret_ref: secret_ref.as_ref()?,
+                },
+            ))
+        })
+        .collect()
+}
+
+fn build_cert_volumes(obj: &crd::AuthentikSpec) -> Vec<Value> {
+    cert_files(obj)
+        .iter()
+        .map(|(_, file)| {
+            json!({
+                "name": file.volume,
+                "secret": {
+                    "secretName": file.secret_ref.name,
+                    "items": [{
+                        "key": file.secret_ref.key,
+                        "path": file.file,
+                        "mode": file.mode
+                    }]
+                }
+            })
+        })
+        .collect()
+}
+
+fn build_cert_mounts(obj: &crd::AuthentikSpec) -> Vec<Value> {
+    cert_files(obj)
+        .iter()
+        .map(|(_, file)| {
+            json!({
+                "name": file.volume,
+                "mountPath": format!("{}/{}", CERTS_PATH, file.volume),
+                "readOnly": true
+            })
+        })
+        .collect()
+}
+
 fn build_env_smtp(obj: Option<&crd::AuthentikSmtp>) -> Vec<EnvVar> {
     let obj = match obj {
         Some(obj) => obj,
         None => return vec![],
     };
 
+    let password = match obj.password_ref.as_ref() {
+        Some(secret_ref) => build_secret_env("AUTHENTIK_EMAIL__PASSWORD", secret_ref),
+        None => EnvVar {
+            name: "AUTHENTIK_EMAIL__PASSWORD".to_string(),
+            value: Some(obj.password.clone()),
+            value_from: None,
+        },
+    };
+
     vec![
         EnvVar {
             name: "AUTHENTIK_EMAIL__HOST".to_string(),
@@ -289,11 +661,7 @@ fn build_env_smtp(obj: Option<&crd::AuthentikSmtp>) -> Vec<EnvVar> {
             value: Some(obj.username.clone()),
             value_from: None,
         },
-        EnvVar {
-            name: "AUTHENTIK_EMAIL__PASSWORD".to_string(),
-            value: Some(obj.password.clone()),
-            value_from: None,
-        },
+        password,
         EnvVar {
             name: "AUTHENTIK_EMAIL__USE_TLS".to_string(),
             value: Some(obj.use_tls.to_string()),
@@ -311,3 +679,94 @@ fn build_env_smtp(obj: Option<&crd::AuthentikSmtp>) -> Vec<EnvVar> {
         },
     ]
 }
+
+fn build_secret_env(name: &str, secret_ref: &crd::AuthentikSecretKeyRef) -> EnvVar {
+    EnvVar {
+        name: name.to_string(),
+        value: None,
+        value_from: Some(EnvVarSource {
+            config_map_key_ref: None,
+            field_ref: None,
+            resource_field_ref: None,
+            secret_key_ref: Some(SecretKeySelector {
+                key: secret_ref.key.clone(),
+                name: Some(secret_ref.name.clone()),
+                optional: Some(false),
+            }),
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::{crd, merge_pod_template};
+
+    fn template() -> serde_json::Value {
+        json!({
+            "metadata": {
+                "labels": { "app.kubernetes.io/name": "authentik" },
+                "annotations": { "checksum": "abc" }
+            },
+            "spec": {
+                "volumes": [{ "name": "media" }],
+                "containers": [{
+                    "name": "authentik",
+                    "volumeMounts": [{ "name": "media", "mountPath": "/media" }]
+                }]
+            }
+        })
+    }
+
+    #[test]
+    fn keeps_the_template_without_overrides() {
+        let mut merged = template();
+        merge_pod_template(&mut merged, &crd::AuthentikPodTemplate::default());
+
+        assert_eq!(merged, template());
+    }
+
+    #[test]
+    fn operator_labels_take_precedence() {
+        let overrides: crd::AuthentikPodTemplate = serde_json::from_value(json!({
+            "labels": { "app.kubernetes.io/name": "other", "team": "a" },
+            "annotations": { "checksum": "def" }
+        }))
+        .unwrap();
+        let mut merged = template();
+        merge_pod_template(&mut merged, &overrides);
+
+        assert_eq!(
+            merged["metadata"]["labels"],
+            json!({ "app.kubernetes.io/name": "authentik", "team": "a" })
+        );
+        assert_eq!(
+            merged["metadata"]["annotations"],
+            json!({ "checksum": "abc" })
+        );
+    }
+
+    #[test]
+    fn appends_volumes_and_containers() {
+        let overrides: crd::AuthentikPodTemplate = serde_json::from_value(json!({
+            "nodeSelector": { "disk": "ssd" },
+            "volumes": [{ "name": "extra", "emptyDir": {} }],
+            "volumeMounts": [{ "name": "extra", "mountPath": "/extra" }],
+            "sidecars": [{ "name": "proxy", "image": "proxy:1" }],
+            "resources": { "limits": { "memory": "1Gi" } }
+        }))
+        .unwrap();
+        let mut merged = template();
+        merge_pod_template(&mut merged, &overrides);
+
+        let spec = &merged["spec"];
+        assert_eq!(spec["nodeSelector"], json!({ "disk": "ssd" }));
+        assert_eq!(spec["volumes"][1]["name"], "extra");
+        assert_eq!(spec["containers"][1]["name"], "proxy");
+
+        let container = &spec["containers"][0];
+        assert_eq!(container["volumeMounts"][1]["mountPath"], "/extra");
+        assert_eq!(container["resources"]["limits"]["memory"], "1Gi");
+    }
+}
@@ -0,0 +1,62 @@
+use anyhow::{anyhow, Result};
+use k8s_openapi::api::policy::v1::PodDisruptionBudget;
+use kube::{Client, ResourceExt};
+use serde_json::{json, Value};
+
+use super::{component, crd, labels};
+
+pub async fn reconcile(obj: &crd::Authentik, client: Client) -> Result<()> {
+    component::reconcile::<PodDisruptionBudget, _>(
+        obj,
+        client,
+        |component| component.pod_disruption_budget.as_ref(),
+        validate,
+        build,
+    )
+    .await
+}
+
+pub async fn cleanup(_obj: &crd::Authentik, _client: Client) -> Result<()> {
+    Ok(())
+}
+
+fn validate(component: &str, budget: &crd::AuthentikDisruptionBudget) -> Result<()> {
+    if budget.min_available.is_some() == budget.max_unavailable.is_some() {
+        return Err(anyhow!(
+            "Exactly one of minAvailable and maxUnavailable must be set for the {} budget.",
+            component
+        ));
+    }
+
+    Ok(())
+}
+
+fn build(
+    name: String,
+    obj: &crd::Authentik,
+    component: &str,
+    budget: &crd::AuthentikDisruptionBudget,
+) -> Value {
+    json!({
+        "apiVersion": "policy/v1",
+        "kind": "PodDisruptionBudget",
+        "metadata": {
+            "name": format!("authentik-{}-{}", name, component),
+            "labels": labels::get_labels(name.clone(), obj.spec.image.tag.to_string(), component.to_string()),
+            "ownerReferences": [{
+                "apiVersion": "ak.dany.dev/v1",
+                "kind": "Authentik",
+                "name": name,
+                "uid": obj.uid().expect("Failed to get UID of Authentik."),
+                "controller": true,
+            }]
+        },
+        "spec": {
+            "minAvailable": budget.min_available,
+            "maxUnavailable": budget.max_unavailable,
+            "selector": {
+                "matchLabels": labels::get_matching_labels(name.clone(), component.to_string())
+            }
+        }
+    })
+}
@@ -1,66 +1,111 @@
-use std::sync::Arc;
+use std::collections::BTreeSet;
 
-use anyhow::anyhow;
 use futures::{future::BoxFuture, FutureExt, StreamExt};
 use k8s_openapi::api::{
     apps::v1::Deployment,
-    core::v1::{Secret, Service, ServiceAccount},
+    autoscaling::v2::HorizontalPodAutoscaler,
+    core::v1::{ConfigMap, Secret, Service, ServiceAccount},
     networking::v1::Ingress,
-    rbac::v1::{ClusterRole, ClusterRoleBinding},
+    policy::v1::PodDisruptionBudget,
+    rbac::v1::{ClusterRole, ClusterRoleBinding, Role, RoleBinding},
 };
 use kube::{
     api::{Api, ListParams, ResourceExt},
-    runtime::{self, controller::Action, finalizer},
-    Client,
+    runtime::{self, reflector::ObjectRef, reflector::Store},
+    Client, Resource,
 };
-use tokio::{sync::Mutex, time::Duration};
 
 mod controller;
 pub mod crd;
 pub mod labels;
 
+mod autoscaler;
+mod bootstrap;
 mod clusteraccount;
+mod component;
 mod deployment;
+mod disruptionbudget;
 mod ingress;
+mod rotation;
 mod secret;
+mod secretkey;
 mod service;
 mod serviceaccount;
 mod servicegroup;
 
 use controller::Controller;
 
-use crate::ReconcileError;
+use crate::config::CONFIG;
 
-use super::list_lp;
+use super::{
+    error_policy, list_lp, reconcile, scoped_api,
+    shard::{self, Shard},
+    Context,
+};
+
+/// Label of referenced secrets and config maps, on which changes the pods are rolled right away.
+pub static WATCH_LABEL: &str = "ak.dany.dev/watch";
 
 pub struct Manager;
 
 impl Manager {
-    pub fn new(client: Client) -> BoxFuture<'static, ()> {
-        let ctrlr = Controller::new(client.clone());
-
-        let servers = Api::<crd::Authentik>::all(client.clone());
-        let deploys = Api::<Deployment>::all(client.clone());
-        let services = Api::<Service>::all(client.clone());
-        let ingresses = Api::<Ingress>::all(client.clone());
-        let secrets = Api::<Secret>::all(client.clone());
-        let serviceaccounts = Api::<ServiceAccount>::all(client.clone());
-        let clusterroles = Api::<ClusterRole>::all(client.clone());
-        let clusterrolebindings = Api::<ClusterRoleBinding>::all(client.clone());
+    #[allow(clippy::new_ret_no_self)]
+    pub fn new(client: Client, ns: Option<String>) -> BoxFuture<'static, ()> {
+        let servers = scoped_api::<crd::Authentik>(client.clone(), ns.as_deref());
+        let deploys = scoped_api::<Deployment>(client.clone(), ns.as_deref());
+        let autoscalers = scoped_api::<HorizontalPodAutoscaler>(client.clone(), ns.as_deref());
+        let budgets = scoped_api::<PodDisruptionBudget>(client.clone(), ns.as_deref());
+        let services = scoped_api::<Service>(client.clone(), ns.as_deref());
+        let ingresses = scoped_api::<Ingress>(client.clone(), ns.as_deref());
+        let secrets = scoped_api::<Secret>(client.clone(), ns.as_deref());
+        let config_maps = scoped_api::<ConfigMap>(client.clone(), ns.as_deref());
+        let serviceaccounts = scoped_api::<ServiceAccount>(client.clone(), ns.as_deref());
         let lp = list_lp("ak-ak");
+        let watched_lp = ListParams::default().labels(&format!("{}=true", WATCH_LABEL));
+
+        let controller = runtime::Controller::new(servers, shard::list_params());
+        let secret_store = controller.store();
+        let config_map_store = controller.store();
+
+        // A namespaced operator gives the instances a role in their namespace, instead of a cluster role.
+        let controller = if CONFIG.namespaced_rbac {
+            controller
+                .owns(
+                    scoped_api::<Role>(client.clone(), ns.as_deref()),
+                    lp.clone(),
+                )
+                .owns(
+                    scoped_api::<RoleBinding>(client.clone(), ns.as_deref()),
+                    lp.clone(),
+                )
+        } else {
+            controller
+                .owns(Api::<ClusterRole>::all(client.clone()), lp.clone())
+                .owns(Api::<ClusterRoleBinding>::all(client.clone()), lp.clone())
+        };
 
-        let drainer = runtime::Controller::new(servers, ListParams::default())
+        let drainer = controller
             .owns(deploys, lp.clone())
+            .owns(autoscalers, lp.clone())
+            .owns(budgets, lp.clone())
             .owns(services, lp.clone())
             .owns(ingresses, lp.clone())
-            .owns(secrets, lp.clone())
+            .owns(secrets.clone(), lp.clone())
             .owns(serviceaccounts, lp.clone())
-            .owns(clusterroles, lp.clone())
-            .owns(clusterrolebindings, lp.clone())
+            // Secrets and config maps referenced by the spec, to roll the pods when they change.
+            // Only labeled ones are watched, so not every secret in scope is streamed to the operator.
+            .watches(secrets, watched_lp.clone(), move |secret| {
+                Self::referencing(&secret_store, &secret, |spec| spec.referenced_secrets())
+            })
+            .watches(config_maps, watched_lp, move |config_map| {
+                Self::referencing(&config_map_store, &config_map, |spec| {
+                    spec.referenced_config_maps()
+                })
+            })
             .run(
-                move |obj, controller| Self::reconcile(obj, controller, client.clone()),
-                move |_, e, _| Self::error_policy(e),
-                Arc::new(Mutex::new(ctrlr)),
+                reconcile::<Controller>,
+                error_policy::<Controller>,
+                Context::new(Controller::new(client.clone()), client, Shard::default()),
             )
             .filter_map(|x| async move { Result::ok(x) })
             .for_each(|_| futures::future::ready(()))
@@ -69,32 +114,18 @@ impl Manager {
         drainer
     }
 
-    async fn reconcile(
-        obj: Arc<crd::Authentik>,
-        controller: Arc<Mutex<Controller>>,
-        client: Client,
-    ) -> Result<Action, ReconcileError> {
-        let ns = obj
-            .namespace()
-            .ok_or(anyhow!("Authentik resource should have a namespace."))?;
-        let servers: Api<crd::Authentik> = Api::namespaced(client, &ns);
-
-        finalizer(&servers, "authentik/ak.dany.dev", obj, |event| async {
-            // Make sure only one reconciliation can be run at the same time.
-            let controller = controller.lock().await;
-
-            match event {
-                finalizer::Event::Apply(server) => controller.reconcile(server).await,
-                finalizer::Event::Cleanup(server) => controller.cleanup(server).await,
-            }
-            .map_err(|e| e.into())
-        })
-        .await
-        .map_err(|e| e.into())
-    }
-
-    fn error_policy(error: &ReconcileError) -> Action {
-        warn!("{}", error);
-        Action::requeue(Duration::from_secs(60))
+    /// Find the instances in the namespace of the object which reference it by name.
+    fn referencing<K: Resource>(
+        store: &Store<crd::Authentik>,
+        obj: &K,
+        references: fn(&crd::AuthentikSpec) -> BTreeSet<String>,
+    ) -> Vec<ObjectRef<crd::Authentik>> {
+        store
+            .state()
+            .iter()
+            .filter(|ak| ak.namespace() == obj.namespace())
+            .filter(|ak| references(&ak.spec).contains(&obj.name_any()))
+            .map(|ak| ObjectRef::from_obj(ak.as_ref()))
+            .collect()
     }
 }
@@ -0,0 +1,189 @@
+use anyhow::{anyhow, Result};
+use chrono::{Duration, Utc};
+use k8s_openapi::api::core::v1::Secret;
+use kube::{runtime::events::EventType, Api, Client, ResourceExt};
+
+use crate::akapi::{
+    auth::{get_operator_client, is_valid_token},
+    token::{CreateToken, CreateTokenBody, DeleteToken, DeleteTokenError, ViewToken},
+    token_identifier_name,
+    user::{Find, FindBody},
+    AkApiRoute, AkClient, API_USER,
+};
+
+use crate::resources::{events::Recorder, locks, status};
+
+use super::{crd, secret};
+
+/// Rotate the operator token, if rotation is enabled and the interval has passed.
+/// The rotation is recorded in the status, which is persisted before the old token is revoked.
+pub async fn reconcile(
+    obj: &crd::Authentik,
+    client: Client,
+    recorder: &Recorder,
+    status: &mut crd::AuthentikStatus,
+) -> Result<()> {
+    let instance = obj.name_any();
+    let ns = obj
+        .namespace()
+        .ok_or(anyhow!("Missing namespace `{}`.", instance.clone()))?;
+    let secrets: Api<Secret> = Api::namespaced(client.clone(), &ns);
+    let secret = secrets
+        .get(&format!("ak-{}-api-operatortoken", instance))
+        .await?;
+    let current_identifier = secret
+        .data
+        .clone()
+        .unwrap_or_default()
+        .remove("identifier")
+        .map(|identifier| String::from_utf8(identifier.0))
+        .transpose()?
+        .unwrap_or(token_identifier_name(&instance, "operatortoken"));
+
+    // Finish an earlier rotation, of which the old token couldn't be revoked yet.
+    if let Some(old_identifier) = status.revoke_token.clone() {
+        if old_identifier != current_identifier {
+            revoke(obj, client.clone(), recorder, &old_identifier).await?;
+        }
+        status.revoke_token = None;
+    }
+
+    let hours = match obj.spec.token_rotation_hours {
+        // Objects created before the minimum was enforced would rotate on every reconcilidation.
+        Some(0) => return Err(anyhow!("tokenRotationHours must be at least 1.")),
+        Some(hours) => hours,
+        None => return Ok(()),
+    };
+
+    // Without an earlier rotation, the age of the secret is used.
+    let last_rotation = status
+        .token_rotated_at
+        .or(secret.metadata.creation_timestamp.map(|time| time.0))
+        .unwrap_or_else(Utc::now);
+    if Utc::now() - last_rotation < Duration::hours(hours.into()) {
+        return Ok(());
+    }
+
+    // Wait for the reconciles using the current token, and keep new ones out until it's replaced.
+    let _instance = locks::exclusive(&ns, &instance).await;
+
+    let ak = get_operator_client(client.clone(), obj)
+        .await?
+        .ok_or(anyhow!(
+            "The operator token is not valid, unable to rotate it."
+        ))?;
+    let old_identifier = current_identifier;
+
+    // Create the new token for the service account.
+    let user = Find::send(
+        &ak,
+        FindBody {
+            username: Some(API_USER.to_string()),
+            ..Default::default()
+        },
+    )
+    .await?
+    .into_iter()
+    .find(|user| user.username == API_USER)
+    .ok_or(anyhow!("The service account was not found!"))?;
+
+    let now = Utc::now();
+    let identifier = token_identifier_name(
+        &instance,
+        &format!("operatortoken-{}", now.format("%Y%m%d%H%M%S")),
+    );
+    CreateToken::send(
+        &ak,
+        CreateTokenBody {
+            identifier: identifier.clone(),
+            intent: "api".to_string(),
+            user: user.pk,
+            description: "Authentication token for the Authentik Operator. Do not delete!"
+                .to_string(),
+            expiring: false,
+        },
+    )
+    .await?;
+
+    // Switch over to the new token, and make sure it works before revoking the old one.
+    if let Err(e) = switch_token(obj, client.clone(), &ak, identifier.clone()).await {
+        // Don't leave the new token behind, as nothing refers to it.
+        match DeleteToken::send(&ak, identifier.clone()).await {
+            Ok(_) | Err(DeleteTokenError::NotFound) => {}
+            Err(delete_error) => warn!(
+                "Failed to delete the unused operator token `{}`: {}",
+                identifier, delete_error
+            ),
+        }
+        return Err(e);
+    }
+
+    // Record the rotation first, so a failed revocation is retried instead of rotating again.
+    status.token_rotated_at = Some(now);
+    status.revoke_token = Some(old_identifier.clone());
+    let servers: Api<crd::Authentik> = Api::namespaced(client.clone(), &ns);
+    status::patch_status(&servers, &instance, status).await?;
+    info!(
+        "Rotated the operator token of `{}`, revoking `{}`.",
+        instance, old_identifier
+    );
+
+    revoke(obj, client, recorder, &old_identifier).await?;
+    status.revoke_token = None;
+    Ok(())
+}
+
+/// Revoke a token which was replaced by a rotation.
+async fn revoke(
+    obj: &crd::Authentik,
+    client: Client,
+    recorder: &Recorder,
+    identifier: &str,
+) -> Result<()> {
+    let ak = get_operator_client(client, obj)
+        .await?
+        .ok_or(anyhow!("The rotated operator token is not valid."))?;
+
+    match DeleteToken::send(&ak, identifier.to_string()).await {
+        Ok(_) | Err(DeleteTokenError::NotFound) => {}
+        Err(e) => return Err(e.into()),
+    }
+
+    recorder
+        .publish(
+            obj,
+            EventType::Normal,
+            "TokenRotated",
+            "Rotate",
+            format!(
+                "Rotated the API token of the operator, revoked `{}`.",
+                identifier
+            ),
+        )
+        .await;
+    info!("Revoked the old operator token `{}`.", identifier);
+    Ok(())
+}
+
+/// Store the newly created token, once it's verified to be valid.
+async fn switch_token(
+    obj: &crd::Authentik,
+    client: Client,
+    ak: &AkClient,
+    identifier: String,
+) -> Result<()> {
+    let token = ViewToken::send(ak, identifier.clone()).await?;
+    if !is_valid_token(client.clone(), obj, &token).await? {
+        return Err(anyhow!(
+            "The new operator token `{}` is not valid.",
+            identifier
+        ));
+    }
+
+    secret::store(obj, client, token, identifier).await
+}
+
+pub async fn cleanup(_obj: &crd::Authentik, _client: Client) -> Result<()> {
+    // Note: The tokens are removed along with the service account.
+    Ok(())
+}
@@ -3,63 +3,109 @@ use base64::encode;
 use k8s_openapi::api::core::v1::Secret;
 use kube::{
     api::{Patch, PatchParams},
+    runtime::events::EventType,
     Api, Client, ResourceExt,
 };
 use serde_json::{json, Value};
 
 use crate::akapi::{
-    auth::{get_valid_secret_token, get_valid_token},
-    token::ViewToken,
+    auth::{
+        get_instance_client, get_operator_client, invalidate, TokenKind, BOOTSTRAP_TOKEN_IDENTIFIER,
+    },
+    token::{DeleteToken, DeleteTokenError, ViewToken},
     token_identifier_name, AkApiRoute, AkClient,
 };
 
+use crate::resources::events::Recorder;
+
 use super::{crd, labels};
 
-pub async fn reconcile(obj: &crd::Authentik, client: Client) -> Result<()> {
+pub async fn reconcile(obj: &crd::Authentik, client: Client, recorder: &Recorder) -> Result<()> {
     let instance = obj
         .metadata
         .name
         .clone()
         .ok_or(anyhow!("Missing instance name.".to_string()))?;
-    let ns = obj
-        .namespace()
-        .ok_or(anyhow!("Missing namespace `{}`.", instance.clone()))?;
 
     // Check if the current secret stored is valid.
     // This is to surpress the logs in Authentik.
-    if get_valid_secret_token(client.clone(), &ns, &instance)
-        .await?
-        .is_some()
-    {
-        return Ok(());
+    if let Some(ak) = get_operator_client(client.clone(), obj).await? {
+        // Checked on every reconcilidation, as pods still running with the bootstrap token can recreate it.
+        return delete_bootstrap_token(obj, recorder, &ak).await;
     }
 
-    // Get the token.
-    let api_key = get_valid_token(client.clone(), &ns, &instance).await?;
-    let ak = AkClient::new(&api_key, &instance, &ns)?;
+    // Get a client, which falls back to the bootstrap token.
+    let ak = get_instance_client(client.clone(), obj).await?;
 
     // Fetch the token from the Authentik server.
-    let token = ViewToken::send(&ak, token_identifier_name(&instance, "operatortoken")).await?;
+    let identifier = token_identifier_name(&instance, "operatortoken");
+    let token = ViewToken::send(&ak, identifier.clone()).await?;
+
+    store(obj, client.clone(), token, identifier).await?;
+    delete_bootstrap_token(obj, recorder, &ak).await
+}
+
+/// Create or patch the secret holding the operator token.
+pub async fn store(
+    obj: &crd::Authentik,
+    client: Client,
+    token: String,
+    identifier: String,
+) -> Result<()> {
+    let instance = obj.name_any();
+    let ns = obj
+        .namespace()
+        .ok_or(anyhow!("Missing namespace `{}`.", instance.clone()))?;
 
-    // Create or patch the secret.
     let api: Api<Secret> = Api::namespaced(client, &ns);
     let name = format!("ak-{}-api-operatortoken", instance);
     api.patch(
         &name,
         &PatchParams::apply("authentik.ak-operator").force(),
-        &Patch::Apply(build(instance.clone(), obj, token)),
+        &Patch::Apply(build(instance.clone(), obj, token, identifier)),
     )
     .await?;
 
+    // An earlier token might have been rejected, make sure the new one is used right away.
+    invalidate(obj, TokenKind::Operator);
     Ok(())
 }
 
-pub async fn cleanup(_obj: &crd::Authentik, _client: Client) -> Result<()> {
+pub async fn cleanup(obj: &crd::Authentik, _client: Client) -> Result<()> {
     // Note: The secret will automatically be cleaned up by Kubernetes.
+    // Forget the tokens, an instance with the same name gets new ones.
+    invalidate(obj, TokenKind::Operator);
+    invalidate(obj, TokenKind::Bootstrap);
     Ok(())
 }
 
-fn build(name: String, obj: &crd::Authentik, token: String) -> Value {
+async fn delete_bootstrap_token(
+    obj: &crd::Authentik,
+    recorder: &Recorder,
+    ak: &AkClient,
+) -> Result<()> {
+    // The bootstrap token is no longer needed once the operator has its own token.
+    match DeleteToken::send(ak, BOOTSTRAP_TOKEN_IDENTIFIER.to_string()).await {
+        Ok(_) => {
+            info!("Deleted the bootstrap token of `{}`.", obj.name_any());
+            recorder
+                .publish(
+                    obj,
+                    EventType::Normal,
+                    "Deleted",
+                    "Delete",
+                    "Deleted the bootstrap token.".to_string(),
+                )
+                .await;
+            invalidate(obj, TokenKind::Bootstrap);
+            Ok(())
+        }
+        Err(DeleteTokenError::NotFound) => Ok(()),
+        Err(e) => Err(e.into()),
+    }
+}
+
+fn build(name: String, obj: &crd::Authentik, token: String, identifier: String) -> Value {
     json!({
         "apiVersion": "v1",
         "kind": "Secret",
@@ -76,7 +122,8 @@ fn build(name: String, obj: &crd::Authentik, token: String) -> Value {
             }]
         },
         "data": {
-            "token": encode(token)
+            "token": encode(token),
+            "identifier": encode(identifier)
         }
     })
 }
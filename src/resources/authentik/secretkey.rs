@@ -0,0 +1,34 @@
+use anyhow::Result;
+use kube::{Client, ResourceExt};
+
+use crate::resources::generated;
+
+use super::{crd, labels};
+
+pub async fn reconcile(obj: &crd::Authentik, client: Client) -> Result<()> {
+    if obj.spec.secret_key.is_some() || obj.spec.secret_key_ref.is_some() {
+        return Ok(());
+    }
+
+    // No secret key is given, so generate one and keep it in a secret.
+    let instance = obj.name_any();
+    generated::ensure_secret(
+        client,
+        obj,
+        &format!("ak-{}-secretkey", instance),
+        labels::get_labels(
+            instance.clone(),
+            obj.spec.image.tag.to_string(),
+            "secretkey".to_string(),
+        ),
+        &[("secretKey", 128)],
+    )
+    .await?;
+
+    Ok(())
+}
+
+pub async fn cleanup(_obj: &crd::Authentik, _client: Client) -> Result<()> {
+    // Note: The secret will automatically be cleaned up by Kubernetes.
+    Ok(())
+}
@@ -55,6 +55,11 @@ fn build(name: String, obj: &crd::Authentik) -> Value {
                 "port": 80,
                 "targetPort": "http",
                 "protocol": "TCP"
+            }, {
+                "name": "https",
+                "port": 443,
+                "targetPort": "https",
+                "protocol": "TCP"
             }],
             "selector": labels::get_matching_labels(name.clone(), "server".to_string())
         }
@@ -1,32 +1,29 @@
 use anyhow::{anyhow, Result};
-use kube::{Client, ResourceExt};
+use kube::{runtime::events::EventType, Client};
 
 use crate::akapi::{
-    auth::get_valid_token,
+    auth::{get_instance_client, get_operator_client},
     token::{CreateToken, CreateTokenBody, CreateTokenError, DeleteToken, DeleteTokenError},
     token_identifier_name,
     user::{
         CreateServiceAccount, CreateServiceAccountBody, CreateServiceAccountError, DeleteAccount,
         DeleteAccountError, Find, FindBody,
     },
-    AkApiRoute, AkClient, API_USER,
+    AkApiRoute, API_USER,
 };
 
+use crate::resources::events::Recorder;
+
 use super::crd;
 
-pub async fn reconcile(obj: &crd::Authentik, client: Client) -> Result<()> {
+pub async fn reconcile(obj: &crd::Authentik, client: Client, recorder: &Recorder) -> Result<()> {
     let instance = obj
         .metadata
         .name
         .clone()
         .ok_or(anyhow!("Missing instance name.".to_string()))?;
-    let ns = obj
-        .namespace()
-        .ok_or(anyhow!("Missing namespace `{}`.", instance.clone()))?;
-
-    // Get the API key.
-    let api_key = get_valid_token(client.clone(), &ns, &instance).await?;
-    let ak = AkClient::new(&api_key, &instance, &ns)?;
+    // Get a client for the instance.
+    let ak = get_instance_client(client.clone(), obj).await?;
 
     // Attempt to create the account.
     let result = CreateServiceAccount::send(
@@ -41,6 +38,15 @@ pub async fn reconcile(obj: &crd::Authentik, client: Client) -> Result<()> {
     match result {
         Ok(account) => {
             info!("Service account created with ID `{}`.", account.user_uid);
+            recorder
+                .publish(
+                    obj,
+                    EventType::Normal,
+                    "Created",
+                    "Create",
+                    format!("Created the service account `{}`.", API_USER),
+                )
+                .await;
         }
         Err(CreateServiceAccountError::ExistsError) => {}
         Err(e) => return Err(e.into()),
@@ -74,6 +80,11 @@ pub async fn reconcile(obj: &crd::Authentik, client: Client) -> Result<()> {
         }
     };
 
+    // The token could have been rotated, so only create the initial token if there's no valid one.
+    if get_operator_client(client.clone(), obj).await?.is_some() {
+        return Ok(());
+    }
+
     // Create the api token if it does not exist.
     let result = CreateToken::send(
         &ak,
@@ -91,6 +102,15 @@ pub async fn reconcile(obj: &crd::Authentik, client: Client) -> Result<()> {
     match result {
         Ok(_) => {
             info!("Token for the service account was created.");
+            recorder
+                .publish(
+                    obj,
+                    EventType::Normal,
+                    "Created",
+                    "Create",
+                    "Created the API token of the operator.".to_string(),
+                )
+                .await;
             Ok(())
         }
         Err(CreateTokenError::ExistsError) => Ok(()),
@@ -99,18 +119,8 @@ pub async fn reconcile(obj: &crd::Authentik, client: Client) -> Result<()> {
 }
 
 pub async fn cleanup(obj: &crd::Authentik, client: Client) -> Result<()> {
-    let instance = obj
-        .metadata
-        .name
-        .clone()
-        .ok_or(anyhow!("Missing instance name.".to_string()))?;
-    let ns = obj
-        .namespace()
-        .ok_or(anyhow!("Missing namespace `{}`.", instance.clone()))?;
-
-    // Get the API key.
-    let api_key = get_valid_token(client.clone(), &ns, &instance).await?;
-    let ak = AkClient::new(&api_key, &instance, &ns)?;
+    // Get a client for the instance.
+    let ak = get_instance_client(client.clone(), obj).await?;
 
     let result = Find::send(
         &ak,
@@ -129,7 +139,7 @@ pub async fn cleanup(obj: &crd::Authentik, client: Client) -> Result<()> {
         }
     };
 
-    let user = match users.iter().find(|&user| &user.username == API_USER) {
+    let user = match users.iter().find(|&user| user.username == API_USER) {
         Some(user) => user,
         None => {
             return Ok(());
@@ -1,39 +1,35 @@
 use anyhow::{anyhow, Result};
-use kube::{Client, ResourceExt};
+use kube::{runtime::events::EventType, Client};
 
 use crate::akapi::{
-    auth::get_valid_token,
+    auth::get_instance_client,
     group::{
         CreateGroup, CreateGroupBody, CreateGroupError, DeleteGroup, DeleteGroupError, FindGroup,
         FindGroupBody,
     },
     service_group_name,
     user::{Find, FindBody},
-    AkApiRoute, AkClient, API_USER,
+    AkApiRoute, API_USER,
 };
 
+use crate::resources::events::Recorder;
+
 use super::crd;
 
-pub async fn reconcile(obj: &crd::Authentik, client: Client) -> Result<()> {
+pub async fn reconcile(obj: &crd::Authentik, client: Client, recorder: &Recorder) -> Result<()> {
     let instance = obj
         .metadata
         .name
         .clone()
         .ok_or(anyhow!("Missing instance name.".to_string()))?;
-    let ns = obj
-        .namespace()
-        .ok_or(anyhow!("Missing namespace `{}`.", instance.clone()))?;
-
-    // Get the API key.
-    let api_key = get_valid_token(client.clone(), &ns, &instance).await?;
-    let ak = AkClient::new(&api_key, &instance, &ns)?;
+    // Get a client for the instance.
+    let ak = get_instance_client(client.clone(), obj).await?;
 
     // Check if the group exists first.
     let groups = FindGroup::send(
         &ak,
         FindGroupBody {
             name: Some(service_group_name(&instance)),
-            ..Default::default()
         },
     )
     .await?;
@@ -74,6 +70,18 @@ pub async fn reconcile(obj: &crd::Authentik, client: Client) -> Result<()> {
     match result {
         Ok(group) => {
             info!("Service group created with ID `{}`.", group.pk);
+            recorder
+                .publish(
+                    obj,
+                    EventType::Normal,
+                    "Created",
+                    "Create",
+                    format!(
+                        "Created the service group `{}`.",
+                        service_group_name(&instance)
+                    ),
+                )
+                .await;
             Ok(())
         }
         Err(CreateGroupError::ExistsError) => Ok(()),
@@ -87,20 +95,14 @@ pub async fn cleanup(obj: &crd::Authentik, client: Client) -> Result<()> {
         .name
         .clone()
         .ok_or(anyhow!("Missing instance name.".to_string()))?;
-    let ns = obj
-        .namespace()
-        .ok_or(anyhow!("Missing namespace `{}`.", instance.clone()))?;
-
-    // Get the API key.
-    let api_key = get_valid_token(client.clone(), &ns, &instance).await?;
-    let ak = AkClient::new(&api_key, &instance, &ns)?;
+    // Get a client for the instance.
+    let ak = get_instance_client(client.clone(), obj).await?;
 
     // Find the group ID.
     let result = FindGroup::send(
         &ak,
         FindGroupBody {
             name: Some(service_group_name(&instance)),
-            ..Default::default()
         },
     )
     .await;
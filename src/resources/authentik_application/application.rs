@@ -1,28 +1,33 @@
 use anyhow::{anyhow, Result};
-use kube::{Client, ResourceExt};
+use kube::{runtime::events::EventType, Client, ResourceExt};
 
 use crate::akapi::{
     application::{
         CreateApplication, DeleteApplication, DeleteApplicationError, GetApplication,
         PatchApplication,
     },
-    auth::get_valid_token,
+    auth::get_client,
     provider::{FindProvider, FindProviderBody},
     types::{Application, Provider},
-    AkApiRoute, AkClient,
+    AkApiRoute,
 };
 
+use crate::resources::events::Recorder;
+
 use super::crd;
 
-pub async fn reconcile(obj: &crd::AuthentikApplication, client: Client) -> Result<()> {
+pub async fn reconcile(
+    obj: &crd::AuthentikApplication,
+    client: Client,
+    recorder: &Recorder,
+) -> Result<String> {
     let instance = obj.spec.authentik_instance.to_string();
     let ns = obj
         .namespace()
         .ok_or(anyhow!("Missing namespace `{}`.", instance.clone()))?;
 
-    // Get the API key.
-    let api_key = get_valid_token(client.clone(), &ns, &instance).await?;
-    let ak = AkClient::new(&api_key, &instance, &ns)?;
+    // Get a client for the instance.
+    let ak = get_client(client.clone(), &ns, &instance).await?;
 
     // Get the provider, returns an error if it can't be found.
     let providers = FindProvider::send(
@@ -42,39 +47,73 @@ pub async fn reconcile(obj: &crd::AuthentikApplication, client: Client) -> Resul
             obj.name_any()
         ))?;
 
-    let new_app = build_application(obj.spec.clone(), &provider);
+    let new_app = build_application(obj.spec.clone(), provider);
     // Get the application, create or patch depending on if it exists.
-    match GetApplication::send(&ak, obj.spec.slug.clone()).await? {
+    let app = match GetApplication::send(&ak, obj.spec.slug.clone()).await? {
         Some(app) => {
             // Compare the serialized versions of the applications.
             // The non-serialized object contains values we don't care about, and can conflict.
             if serde_json::to_string(&app)? != serde_json::to_string(&new_app)? {
                 // There is a difference in the objects, patching it.
-                PatchApplication::send(&ak, new_app).await?;
+                let app = PatchApplication::send(&ak, new_app).await?;
+                recorder
+                    .publish(
+                        obj,
+                        EventType::Normal,
+                        "Patched",
+                        "Patch",
+                        format!("Patched application `{}`.", obj.spec.slug),
+                    )
+                    .await;
+                app
+            } else {
+                app
             }
         }
         None => {
-            CreateApplication::send(&ak, new_app).await?;
+            let app = CreateApplication::send(&ak, new_app).await?;
+            recorder
+                .publish(
+                    obj,
+                    EventType::Normal,
+                    "Created",
+                    "Create",
+                    format!("Created application `{}`.", obj.spec.slug),
+                )
+                .await;
+            app
         }
     };
 
-    Ok(())
+    Ok(app.pk)
 }
 
-pub async fn cleanup(obj: &crd::AuthentikApplication, client: Client) -> Result<()> {
+pub async fn cleanup(
+    obj: &crd::AuthentikApplication,
+    client: Client,
+    recorder: &Recorder,
+) -> Result<()> {
     let instance = obj.spec.authentik_instance.to_string();
     let ns = obj
         .namespace()
         .ok_or(anyhow!("Missing namespace `{}`.", instance.clone()))?;
 
-    // Get the API key.
-    let api_key = get_valid_token(client.clone(), &ns, &instance).await?;
-    let ak = AkClient::new(&api_key, &instance, &ns)?;
+    // Get a client for the instance.
+    let ak = get_client(client.clone(), &ns, &instance).await?;
 
     // Delete the application.
     match DeleteApplication::send(&ak, obj.spec.slug.clone()).await {
         Ok(_) => {
             info!("Deleted application `{}`.", obj.spec.name);
+            recorder
+                .publish(
+                    obj,
+                    EventType::Normal,
+                    "Deleted",
+                    "Delete",
+                    format!("Deleted application `{}`.", obj.spec.slug),
+                )
+                .await;
         }
         Err(DeleteApplicationError::NotFound) => {}
         Err(e) => return Err(e.into()),
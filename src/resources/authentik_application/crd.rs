@@ -4,6 +4,8 @@ use regex::Regex;
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
+use crate::resources::status::ObjectStatus;
+
 lazy_static! {
     static ref SLUG_VALIDATOR: Regex = Regex::new(r"^[-a-zA-Z0-9_]+$").unwrap();
 }
@@ -15,6 +17,10 @@ lazy_static! {
     group = "ak.dany.dev",
     version = "v1",
     shortname = "akapp",
+    status = "ObjectStatus",
+    printcolumn = r#"{"name":"Instance", "type":"string", "jsonPath":".spec.authentikInstance"}"#,
+    printcolumn = r#"{"name":"Ready", "type":"string", "jsonPath":".status.conditions[?(@.type==\"Ready\")].status"}"#,
+    printcolumn = r#"{"name":"Age", "type":"date", "jsonPath":".metadata.creationTimestamp"}"#,
     namespaced
 )]
 pub struct AuthentikApplicationSpec {
@@ -1,15 +1,7 @@
-use std::sync::Arc;
-
-use anyhow::anyhow;
 use futures::{future::BoxFuture, FutureExt, StreamExt};
-use kube::{
-    api::{Api, ListParams, ResourceExt},
-    runtime::{self, controller::Action, finalizer},
-    Client,
-};
-use tokio::{sync::Mutex, time::Duration};
+use kube::{api::ListParams, runtime, Client};
 
-use crate::ReconcileError;
+use crate::metrics;
 
 mod controller;
 pub mod crd;
@@ -18,57 +10,34 @@ mod application;
 
 use controller::Controller;
 
+use super::{error_policy, reconcile, scoped_api, shard::Shard, Context};
+
 pub struct Manager;
 
 impl Manager {
-    pub fn new(client: Client) -> BoxFuture<'static, ()> {
-        let ctrlr = Controller::new(client.clone());
-        let apps = Api::<crd::AuthentikApplication>::all(client.clone());
+    #[allow(clippy::new_ret_no_self)]
+    pub fn new(client: Client, ns: Option<String>) -> BoxFuture<'static, ()> {
+        let (shard, shard_watch) = Shard::new(client.clone(), ns.as_deref());
+        let apps = scoped_api::<crd::AuthentikApplication>(client.clone(), ns.as_deref());
+
+        let controller = runtime::Controller::new(apps, ListParams::default());
+        metrics::count_objects(controller.store(), |obj| {
+            obj.spec.authentik_instance.clone()
+        });
 
-        let drainer = runtime::Controller::new(apps, ListParams::default())
+        let drainer = controller
             .run(
-                move |obj, controller| Self::reconcile(obj, controller, client.clone()),
-                move |_, e, _| Self::error_policy(e),
-                Arc::new(Mutex::new(ctrlr)),
+                reconcile::<Controller>,
+                error_policy::<Controller>,
+                Context::new(Controller::new(client.clone()), client, shard),
             )
             .filter_map(|x| async move { Result::ok(x) })
             .for_each(|_| futures::future::ready(()))
             .boxed();
 
-        drainer
-    }
-
-    async fn reconcile(
-        obj: Arc<crd::AuthentikApplication>,
-        controller: Arc<Mutex<Controller>>,
-        client: Client,
-    ) -> Result<Action, ReconcileError> {
-        let ns = obj.namespace().ok_or(anyhow!(
-            "Authentik application resource should have a namespace."
-        ))?;
-        let servers: Api<crd::AuthentikApplication> = Api::namespaced(client, &ns);
-
-        finalizer(
-            &servers,
-            "authentik-application/ak.dany.dev",
-            obj,
-            |event| async {
-                // Make sure only one reconciliation can be run at the same time.
-                let controller = controller.lock().await;
-
-                match event {
-                    finalizer::Event::Apply(server) => controller.reconcile(server).await,
-                    finalizer::Event::Cleanup(server) => controller.cleanup(server).await,
-                }
-                .map_err(|e| e.into())
-            },
-        )
-        .await
-        .map_err(|e| e.into())
-    }
-
-    fn error_policy(error: &ReconcileError) -> Action {
-        warn!("{}", error);
-        Action::requeue(Duration::from_secs(60))
+        // The instances of the shard are watched for as long as the controller runs.
+        futures::future::select(drainer, shard_watch)
+            .map(|_| ())
+            .boxed()
     }
 }
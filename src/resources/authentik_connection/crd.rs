@@ -0,0 +1,26 @@
+use kube::CustomResource;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::resources::authentik::crd::{
+    AuthentikCaBundle, AuthentikClientCertificate, AuthentikSecretKeyRef,
+};
+
+#[derive(CustomResource, Deserialize, Serialize, Clone, Debug, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+#[kube(
+    kind = "AuthentikConnection",
+    group = "ak.dany.dev",
+    version = "v1",
+    shortname = "akconn",
+    printcolumn = r#"{"name":"URL", "type":"string", "jsonPath":".spec.url"}"#,
+    printcolumn = r#"{"name":"Age", "type":"date", "jsonPath":".metadata.creationTimestamp"}"#,
+    namespaced
+)]
+pub struct AuthentikConnectionSpec {
+    #[validate(url)]
+    pub url: String,
+    pub token_ref: AuthentikSecretKeyRef,
+    pub ca: Option<AuthentikCaBundle>,
+    pub client_certificate: Option<AuthentikClientCertificate>,
+}
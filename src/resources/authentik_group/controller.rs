@@ -1,36 +1,87 @@
 use std::sync::Arc;
 
-use anyhow::Result;
-use kube::{runtime::controller::Action, Client, ResourceExt};
-use tokio::time::Duration;
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use kube::{api::Api, runtime::controller::Action, Client, ResourceExt};
+
+use crate::resources::{events::Recorder, requeue, status, Reconciler};
 
 use super::{crd, group};
 
 pub struct Controller {
     client: Client,
+    recorder: Recorder,
 }
 
 impl Controller {
     pub fn new(client: Client) -> Self {
-        Self { client }
+        Self {
+            recorder: Recorder::new(client.clone()),
+            client,
+        }
+    }
+
+    async fn cleanup_parts(&self, obj: &crd::AuthentikGroup) -> Result<()> {
+        group::cleanup(obj, self.client.clone(), &self.recorder).await?;
+
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl Reconciler for Controller {
+    type Kind = crd::AuthentikGroup;
+
+    const FINALIZER: &'static str = "authentik-group/ak.dany.dev";
+
+    fn instance(obj: &crd::AuthentikGroup) -> Option<&str> {
+        Some(&obj.spec.authentik_instance)
     }
 
-    pub async fn reconcile(&self, obj: Arc<crd::AuthentikGroup>) -> Result<Action> {
+    async fn reconcile(&self, obj: Arc<crd::AuthentikGroup>) -> Result<Action> {
         info!(
             "Starting reconcilidation of Authentik group `{}`.",
             obj.name_any()
         );
+        let ns = obj
+            .namespace()
+            .ok_or(anyhow!("Missing namespace `{}`.", obj.name_any()))?;
+        let api: Api<crd::AuthentikGroup> = Api::namespaced(self.client.clone(), &ns);
+
+        // Reconcile all parts, and reflect the outcome in the status.
+        let mut status = obj.status.clone().unwrap_or_default();
+        status.observed_generation = obj.metadata.generation;
+        let result = group::reconcile(&obj, self.client.clone(), &self.recorder).await;
+
+        if let Err(e) = &result {
+            self.recorder
+                .warn(obj.as_ref(), "ReconcileFailed", "Reconcile", e.to_string())
+                .await;
+        } else {
+            self.recorder.resolved(obj.as_ref());
+        }
+
+        match &result {
+            Ok(pk) => status.succeeded(pk.clone()),
+            Err(e) => status.failed(e),
+        }
 
-        // Reconcile all parts.
-        group::reconcile(&obj, self.client.clone()).await?;
+        status::update(&api, obj.as_ref(), obj.status.as_ref(), &status, result).await?;
 
-        info!("Reconcilidation of Authentik group `{}` finished successfully, re-queued for 30 minutes.", obj.name_any());
-        Ok(Action::requeue(Duration::from_secs(30 * 60)))
+        let interval = requeue::resync_interval(obj.as_ref());
+        info!("Reconcilidation of Authentik group `{}` finished successfully, re-queued in {} seconds.", obj.name_any(), interval.as_secs());
+        Ok(Action::requeue(interval))
     }
 
-    pub async fn cleanup(&self, obj: Arc<crd::AuthentikGroup>) -> Result<Action> {
-        group::cleanup(obj.as_ref(), self.client.clone()).await?;
+    async fn cleanup(&self, obj: Arc<crd::AuthentikGroup>) -> Result<Action> {
+        if let Err(e) = self.cleanup_parts(obj.as_ref()).await {
+            self.recorder
+                .warn(obj.as_ref(), "CleanupFailed", "Cleanup", e.to_string())
+                .await;
+            return Err(e);
+        }
 
+        self.recorder.resolved(obj.as_ref());
         Ok(Action::await_change())
     }
 }
@@ -1,40 +1,43 @@
 use anyhow::{anyhow, Result};
-use kube::{Client, ResourceExt};
+use kube::{runtime::events::EventType, Client, ResourceExt};
 
 use crate::akapi::{
-    auth::get_valid_token,
+    auth::get_client,
     group::{
         CreateGroup, CreateGroupBody, CreateGroupError, DeleteGroup, DeleteGroupError, FindGroup,
         FindGroupBody,
     },
-    AkApiRoute, AkClient,
+    AkApiRoute,
 };
 
+use crate::resources::events::Recorder;
+
 use super::crd;
 
-pub async fn reconcile(obj: &crd::AuthentikGroup, client: Client) -> Result<()> {
+pub async fn reconcile(
+    obj: &crd::AuthentikGroup,
+    client: Client,
+    recorder: &Recorder,
+) -> Result<Option<String>> {
     let instance = obj.spec.authentik_instance.to_string();
     let ns = obj
         .namespace()
         .ok_or(anyhow!("Missing namespace `{}`.", instance.clone()))?;
 
-    // Get the API key.
-    let api_key = get_valid_token(client.clone(), &ns, &instance).await?;
-    let ak = AkClient::new(&api_key, &instance, &ns)?;
+    // Get a client for the instance.
+    let ak = get_client(client.clone(), &ns, &instance).await?;
 
     // Check if the group already exists.
     let result = FindGroup::send(
         &ak,
         FindGroupBody {
             name: Some(obj.spec.name.clone()),
-            ..Default::default()
         },
     )
     .await?;
 
-    match result.iter().find(|&group| group.name == obj.spec.name) {
-        Some(_) => return Ok(()),
-        None => (),
+    if let Some(group) = result.iter().find(|&group| group.name == obj.spec.name) {
+        return Ok(Some(group.pk.clone()));
     }
 
     // Get the ID of the parent.
@@ -43,7 +46,6 @@ pub async fn reconcile(obj: &crd::AuthentikGroup, client: Client) -> Result<()>
             &ak,
             FindGroupBody {
                 name: Some(parent.clone()),
-                ..Default::default()
             },
         )
         .await?;
@@ -69,28 +71,37 @@ pub async fn reconcile(obj: &crd::AuthentikGroup, client: Client) -> Result<()>
     .await;
 
     match result {
-        Ok(_) => Ok(()),
-        Err(CreateGroupError::ExistsError) => Ok(()),
+        Ok(group) => {
+            recorder
+                .publish(
+                    obj,
+                    EventType::Normal,
+                    "Created",
+                    "Create",
+                    format!("Created group `{}`.", obj.spec.name),
+                )
+                .await;
+            Ok(Some(group.pk))
+        }
+        Err(CreateGroupError::ExistsError) => Ok(None),
         Err(e) => Err(e.into()),
     }
 }
 
-pub async fn cleanup(obj: &crd::AuthentikGroup, client: Client) -> Result<()> {
+pub async fn cleanup(obj: &crd::AuthentikGroup, client: Client, recorder: &Recorder) -> Result<()> {
     let instance = obj.spec.authentik_instance.to_string();
     let ns = obj
         .namespace()
         .ok_or(anyhow!("Missing namespace `{}`.", instance.clone()))?;
 
-    // Get the API key.
-    let api_key = get_valid_token(client.clone(), &ns, &instance).await?;
-    let ak = AkClient::new(&api_key, &instance, &ns)?;
+    // Get a client for the instance.
+    let ak = get_client(client.clone(), &ns, &instance).await?;
 
     // Find the ID of the group to delete.
     let result = FindGroup::send(
         &ak,
         FindGroupBody {
             name: Some(obj.spec.name.clone()),
-            ..Default::default()
         },
     )
     .await?;
@@ -103,7 +114,16 @@ pub async fn cleanup(obj: &crd::AuthentikGroup, client: Client) -> Result<()> {
     // Delete the group.
     match DeleteGroup::send(&ak, group_id).await {
         Ok(_) => {
-            info!("Deleted service group.");
+            info!("Deleted group `{}`.", obj.spec.name);
+            recorder
+                .publish(
+                    obj,
+                    EventType::Normal,
+                    "Deleted",
+                    "Delete",
+                    format!("Deleted group `{}`.", obj.spec.name),
+                )
+                .await;
             Ok(())
         }
         Err(DeleteGroupError::NotFound) => Ok(()),
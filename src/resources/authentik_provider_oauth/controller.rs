@@ -1,103 +1,95 @@
 use std::sync::Arc;
 
 use anyhow::{anyhow, Result};
-use kube::{
-    api::{Patch, PatchParams},
-    runtime::controller::Action,
-    Api, Client, ResourceExt,
-};
-use rand::{distributions::Alphanumeric, thread_rng, Rng};
-use serde_json::{json, Map};
-use tokio::time::Duration;
+use async_trait::async_trait;
+use kube::{runtime::controller::Action, Api, Client, ResourceExt};
+
+use crate::resources::{events::Recorder, requeue, status, Reconciler};
 
 use super::{crd, provider, secret};
 
 pub struct Controller {
     client: Client,
+    recorder: Recorder,
 }
 
 impl Controller {
     pub fn new(client: Client) -> Self {
-        Self { client }
+        Self {
+            recorder: Recorder::new(client.clone()),
+            client,
+        }
+    }
+
+    async fn reconcile_parts(&self, obj: &crd::AuthentikOAuthProvider) -> Result<usize> {
+        let pk = provider::reconcile(obj, self.client.clone(), &self.recorder).await?;
+        secret::reconcile(obj, self.client.clone()).await?;
+
+        Ok(pk)
+    }
+
+    async fn cleanup_parts(&self, obj: &crd::AuthentikOAuthProvider) -> Result<()> {
+        secret::cleanup(obj, self.client.clone()).await?;
+        provider::cleanup(obj, self.client.clone(), &self.recorder).await?;
+
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl Reconciler for Controller {
+    type Kind = crd::AuthentikOAuthProvider;
+
+    const FINALIZER: &'static str = "authentik-oauth/ak.dany.dev";
+
+    fn instance(obj: &crd::AuthentikOAuthProvider) -> Option<&str> {
+        Some(&obj.spec.authentik_instance)
     }
 
-    pub async fn reconcile(&self, obj: Arc<crd::AuthentikOAuthProvider>) -> Result<Action> {
+    async fn reconcile(&self, obj: Arc<crd::AuthentikOAuthProvider>) -> Result<Action> {
         info!(
             "Starting reconcilidation of Authentik oauth provider `{}`.",
             obj.name_any()
         );
-        let name = obj.name_any();
         let ns = obj
             .namespace()
             .ok_or(anyhow!("Missing namespace `{}`.", obj.name_any()))?;
         let servers: Api<crd::AuthentikOAuthProvider> = Api::namespaced(self.client.clone(), &ns);
 
-        // Generate any default values.
-        let mut obj = obj.as_ref().clone();
-        let changed = self.autofill(&mut obj, &servers, name.as_str()).await?;
-
-        if changed {
-            // CRD updated, requeue so the changes are actually reflected.
-            return Ok(Action::requeue(Duration::from_secs(1)));
+        // Reconcile all parts, and reflect the outcome in the status.
+        let mut status = obj.status.clone().unwrap_or_default();
+        status.observed_generation = obj.metadata.generation;
+        let result = self.reconcile_parts(&obj).await;
+
+        if let Err(e) = &result {
+            self.recorder
+                .warn(obj.as_ref(), "ReconcileFailed", "Reconcile", e.to_string())
+                .await;
+        } else {
+            self.recorder.resolved(obj.as_ref());
         }
 
-        // Reconcile all parts.
-        provider::reconcile(&obj, self.client.clone()).await?;
-        secret::reconcile(&obj, self.client.clone()).await?;
-
-        info!("Reconcilidation of Authentik oauth provider `{}` finished successfully, re-queued for 30 minutes.", obj.name_any());
-        Ok(Action::requeue(Duration::from_secs(30 * 60)))
-    }
-
-    pub async fn cleanup(&self, obj: Arc<crd::AuthentikOAuthProvider>) -> Result<Action> {
-        secret::cleanup(obj.as_ref(), self.client.clone()).await?;
-        provider::cleanup(obj.as_ref(), self.client.clone()).await?;
-
-        Ok(Action::await_change())
-    }
-
-    async fn autofill(
-        &self,
-        obj: &mut crd::AuthentikOAuthProvider,
-        api: &Api<crd::AuthentikOAuthProvider>,
-        name: &str,
-    ) -> Result<bool> {
-        let mut values = Map::new();
-
-        if obj.spec.client_id.is_none() {
-            // Create the secret if it does not yet exist
-            let client_id: String = thread_rng()
-                .sample_iter(&Alphanumeric)
-                .take(128)
-                .map(char::from)
-                .collect();
-
-            values.insert("clientId".to_string(), json!(client_id));
+        match &result {
+            Ok(pk) => status.succeeded(Some(pk.to_string())),
+            Err(e) => status.failed(e),
         }
 
-        if obj.spec.client_secret.is_none() {
-            // Create the secret if it does not yet exist
-            let secret: String = thread_rng()
-                .sample_iter(&Alphanumeric)
-                .take(255)
-                .map(char::from)
-                .collect();
+        status::update(&servers, obj.as_ref(), obj.status.as_ref(), &status, result).await?;
 
-            values.insert("clientSecret".to_string(), json!(secret));
-        }
+        let interval = requeue::resync_interval(obj.as_ref());
+        info!("Reconcilidation of Authentik oauth provider `{}` finished successfully, re-queued in {} seconds.", obj.name_any(), interval.as_secs());
+        Ok(Action::requeue(interval))
+    }
 
-        if values.is_empty() {
-            return Ok(false);
+    async fn cleanup(&self, obj: Arc<crd::AuthentikOAuthProvider>) -> Result<Action> {
+        if let Err(e) = self.cleanup_parts(obj.as_ref()).await {
+            self.recorder
+                .warn(obj.as_ref(), "CleanupFailed", "Cleanup", e.to_string())
+                .await;
+            return Err(e);
         }
 
-        let pp = PatchParams::apply("authentik.ak-operator").force();
-        let patched_secret = Patch::Apply(json!({
-            "apiVersion": "ak.dany.dev/v1",
-            "kind": "AuthentikOAuthProvider",
-            "spec": values
-        }));
-
-        api.patch(&name, &pp, &patched_secret).await?;
-        Ok(true)
+        self.recorder.resolved(obj.as_ref());
+        Ok(Action::await_change())
     }
 }
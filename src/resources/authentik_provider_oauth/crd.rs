@@ -1,7 +1,11 @@
+use std::fmt;
+
 use kube::CustomResource;
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
+use crate::resources::status::ObjectStatus;
+
 #[derive(CustomResource, Deserialize, Serialize, Clone, Debug, JsonSchema)]
 #[serde(rename_all = "camelCase")]
 #[kube(
@@ -9,6 +13,10 @@ use serde::{Deserialize, Serialize};
     group = "ak.dany.dev",
     version = "v1",
     shortname = "akoauth",
+    status = "ObjectStatus",
+    printcolumn = r#"{"name":"Instance", "type":"string", "jsonPath":".spec.authentikInstance"}"#,
+    printcolumn = r#"{"name":"Ready", "type":"string", "jsonPath":".status.conditions[?(@.type==\"Ready\")].status"}"#,
+    printcolumn = r#"{"name":"Age", "type":"date", "jsonPath":".metadata.creationTimestamp"}"#,
     namespaced
 )]
 pub struct AuthentikOAuthProviderSpec {
@@ -45,11 +53,11 @@ pub enum ClientType {
     Public,
 }
 
-impl ToString for ClientType {
-    fn to_string(&self) -> String {
+impl fmt::Display for ClientType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
-            ClientType::Confidential => "confidential".to_string(),
-            ClientType::Public => "public".to_string(),
+            ClientType::Confidential => write!(f, "confidential"),
+            ClientType::Public => write!(f, "public"),
         }
     }
 }
@@ -1,10 +1,10 @@
 use std::collections::{hash_map::RandomState, HashSet};
 
 use anyhow::{anyhow, Result};
-use kube::{Client, ResourceExt};
+use kube::{runtime::events::EventType, Client, ResourceExt};
 
 use crate::akapi::{
-    auth::get_valid_token,
+    auth::get_client,
     certificate::{FindCertificate, FindCertificateBody},
     flow::GetFlow,
     propertymappings::{FindScopeMapping, FindScopeMappingBody},
@@ -13,20 +13,25 @@ use crate::akapi::{
         FindOAuthProviderBody, PatchOAuthProvider,
     },
     types::{Flow, OAuthProvider},
-    AkApiRoute, AkClient,
+    AkApiRoute,
 };
 
-use super::crd;
+use crate::resources::{events::Recorder, generated};
 
-pub async fn reconcile(obj: &crd::AuthentikOAuthProvider, client: Client) -> Result<()> {
+use super::{crd, labels};
+
+pub async fn reconcile(
+    obj: &crd::AuthentikOAuthProvider,
+    client: Client,
+    recorder: &Recorder,
+) -> Result<usize> {
     let instance = obj.spec.authentik_instance.to_string();
     let ns = obj
         .namespace()
         .ok_or(anyhow!("Missing namespace `{}`.", instance.clone()))?;
 
-    // Get the API key.
-    let api_key = get_valid_token(client.clone(), &ns, &instance).await?;
-    let ak = AkClient::new(&api_key, &instance, &ns)?;
+    // Get a client for the instance.
+    let ak = get_client(client.clone(), &ns, &instance).await?;
 
     // Get the flow.
     let flow = GetFlow::send(&ak, obj.spec.flow.clone()).await?;
@@ -74,6 +79,9 @@ pub async fn reconcile(obj: &crd::AuthentikOAuthProvider, client: Client) -> Res
         None
     };
 
+    // Get the client credentials, generating them if they are not given.
+    let credentials = get_credentials(obj, client.clone()).await?;
+
     // Check if the provider already exists.
     let providers = FindOAuthProvider::send(
         &ak,
@@ -86,7 +94,7 @@ pub async fn reconcile(obj: &crd::AuthentikOAuthProvider, client: Client) -> Res
         .iter()
         .find(|&provider| provider.name == obj.spec.name);
 
-    let new_provider = build_provider(&obj.spec, provider, &flow, signing_key, scopes);
+    let new_provider = build_provider(&obj.spec, provider, &flow, signing_key, scopes, credentials);
     match provider {
         Some(provider) => {
             // Compare the serialized versions of the provider.
@@ -94,26 +102,49 @@ pub async fn reconcile(obj: &crd::AuthentikOAuthProvider, client: Client) -> Res
             if serde_json::to_string(&provider)? != serde_json::to_string(&new_provider)? {
                 // There is a difference in the objects, patching it.
                 PatchOAuthProvider::send(&ak, new_provider).await?;
+                recorder
+                    .publish(
+                        obj,
+                        EventType::Normal,
+                        "Patched",
+                        "Patch",
+                        format!("Patched OAuth provider `{}`.", obj.spec.name),
+                    )
+                    .await;
             }
+
+            Ok(provider.pk)
         }
         None => {
             // Create the provider.
-            CreateOAuthProvider::send(&ak, new_provider).await?;
+            let provider = CreateOAuthProvider::send(&ak, new_provider).await?;
+            recorder
+                .publish(
+                    obj,
+                    EventType::Normal,
+                    "Created",
+                    "Create",
+                    format!("Created OAuth provider `{}`.", obj.spec.name),
+                )
+                .await;
+
+            Ok(provider.pk)
         }
     }
-
-    Ok(())
 }
 
-pub async fn cleanup(obj: &crd::AuthentikOAuthProvider, client: Client) -> Result<()> {
+pub async fn cleanup(
+    obj: &crd::AuthentikOAuthProvider,
+    client: Client,
+    recorder: &Recorder,
+) -> Result<()> {
     let instance = obj.spec.authentik_instance.to_string();
     let ns = obj
         .namespace()
         .ok_or(anyhow!("Missing namespace `{}`.", instance.clone()))?;
 
-    // Get the API key.
-    let api_key = get_valid_token(client.clone(), &ns, &instance).await?;
-    let ak = AkClient::new(&api_key, &instance, &ns)?;
+    // Get a client for the instance.
+    let ak = get_client(client.clone(), &ns, &instance).await?;
 
     // Find the provider.
     let providers = FindOAuthProvider::send(
@@ -133,9 +164,18 @@ pub async fn cleanup(obj: &crd::AuthentikOAuthProvider, client: Client) -> Resul
     };
 
     // Delete the provider.
-    match DeleteOAuthProvider::send(&ak, provider.pk.clone()).await {
+    match DeleteOAuthProvider::send(&ak, provider.pk).await {
         Ok(_) => {
             info!("OAuth provider `{}` was deleted.", obj.name_any());
+            recorder
+                .publish(
+                    obj,
+                    EventType::Normal,
+                    "Deleted",
+                    "Delete",
+                    format!("Deleted OAuth provider `{}`.", obj.spec.name),
+                )
+                .await;
             Ok(())
         }
         Err(DeleteOAuthProviderError::NotFound) => Ok(()),
@@ -143,12 +183,65 @@ pub async fn cleanup(obj: &crd::AuthentikOAuthProvider, client: Client) -> Resul
     }
 }
 
+async fn get_credentials(
+    obj: &crd::AuthentikOAuthProvider,
+    client: Client,
+) -> Result<(String, String)> {
+    let mut keys = Vec::new();
+    if obj.spec.client_id.is_none() {
+        keys.push(("clientId", 128));
+    }
+    if obj.spec.client_secret.is_none() {
+        keys.push(("clientSecret", 255));
+    }
+
+    if keys.is_empty() {
+        return Ok((
+            obj.spec.client_id.clone().unwrap_or_default(),
+            obj.spec.client_secret.clone().unwrap_or_default(),
+        ));
+    }
+
+    // Generated values are kept in a separate secret, so they are stable between reconcilidations.
+    let mut values = generated::ensure_secret(
+        client,
+        obj,
+        &format!(
+            "ak-{}-oauth-{}-generated",
+            obj.spec.authentik_instance,
+            obj.name_any()
+        ),
+        labels::get_labels(
+            obj.spec.authentik_instance.to_string(),
+            "generated".to_string(),
+        ),
+        &keys,
+    )
+    .await?;
+
+    let client_id = match obj.spec.client_id.clone() {
+        Some(client_id) => client_id,
+        None => values
+            .remove("clientId")
+            .ok_or(anyhow!("Generated client ID is missing."))?,
+    };
+    let client_secret = match obj.spec.client_secret.clone() {
+        Some(client_secret) => client_secret,
+        None => values
+            .remove("clientSecret")
+            .ok_or(anyhow!("Generated client secret is missing."))?,
+    };
+
+    Ok((client_id, client_secret))
+}
+
 fn build_provider(
     spec: &crd::AuthentikOAuthProviderSpec,
     old_provider: Option<&OAuthProvider>,
     flow: &Flow,
     signing_key: Option<String>,
     scopes: Vec<String>,
+    (client_id, client_secret): (String, String),
 ) -> OAuthProvider {
     let mappings = old_provider
         .and_then(|p| p.property_mappings.clone())
@@ -167,8 +260,8 @@ fn build_provider(
         authorization_flow: flow.pk.clone(),
         property_mappings: Some(mappings),
         client_type: Some(spec.client_type.clone()),
-        client_id: spec.client_id.clone(),
-        client_secret: spec.client_secret.clone(),
+        client_id: Some(client_id),
+        client_secret: Some(client_secret),
         include_claims_in_id_token: spec.claims_in_token,
         redirect_uris: Some(spec.redirect_uris.join("\n")),
         access_code_validity: Some(spec.access_code_validity.clone()),
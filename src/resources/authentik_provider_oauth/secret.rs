@@ -8,10 +8,10 @@ use kube::{
 use serde_json::{json, Value};
 
 use crate::akapi::{
-    auth::get_valid_token,
+    auth::get_client,
     provider::{FindOAuthProvider, FindOAuthProviderBody},
     types::OAuthProvider,
-    AkApiRoute, AkClient,
+    AkApiRoute,
 };
 
 use super::{crd, labels};
@@ -22,9 +22,8 @@ pub async fn reconcile(obj: &crd::AuthentikOAuthProvider, client: Client) -> Res
         .namespace()
         .ok_or(anyhow!("Missing namespace `{}`.", instance.clone()))?;
 
-    // Get the API key.
-    let api_key = get_valid_token(client.clone(), &ns, &instance).await?;
-    let ak = AkClient::new(&api_key, &instance, &ns)?;
+    // Get a client for the instance.
+    let ak = get_client(client.clone(), &ns, &instance).await?;
 
     // Get the provider from the API.
     let providers = FindOAuthProvider::send(
@@ -75,17 +74,17 @@ fn build(obj: &crd::AuthentikOAuthProvider, secret_name: &str, provider: &OAuthP
     let client_id = provider
         .client_id
         .as_ref()
-        .map(|c| encode(c))
+        .map(encode)
         .unwrap_or("".to_string());
     let client_secret = provider
         .client_secret
         .as_ref()
-        .map(|c| encode(c))
+        .map(encode)
         .unwrap_or("".to_string());
     let redirect_uris = provider
         .redirect_uris
         .as_ref()
-        .map(|c| encode(c))
+        .map(encode)
         .unwrap_or("".to_string());
 
     json!({
@@ -1,40 +1,101 @@
 use std::sync::Arc;
 
-use anyhow::Result;
-use kube::{api::ResourceExt, runtime::controller::Action, Client};
-use tokio::time::Duration;
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use kube::{
+    api::{Api, ResourceExt},
+    runtime::controller::Action,
+    Client,
+};
+
+use crate::resources::{events::Recorder, requeue, status, Reconciler};
 
 use super::{crd, group, password, user};
 
 pub struct Controller {
     client: Client,
+    recorder: Recorder,
 }
 
 impl Controller {
     pub fn new(client: Client) -> Self {
-        Self { client }
+        Self {
+            recorder: Recorder::new(client.clone()),
+            client,
+        }
+    }
+
+    async fn reconcile_parts(&self, obj: &crd::AuthentikUser) -> Result<usize> {
+        let pk = user::reconcile(obj, self.client.clone(), &self.recorder).await?;
+        password::reconcile(obj, self.client.clone(), &self.recorder).await?;
+        group::reconcile(obj, self.client.clone()).await?;
+
+        Ok(pk)
     }
 
-    pub async fn reconcile(&self, obj: Arc<crd::AuthentikUser>) -> Result<Action> {
+    async fn cleanup_parts(&self, obj: &crd::AuthentikUser) -> Result<()> {
+        group::cleanup(obj, self.client.clone()).await?;
+        password::cleanup(obj, self.client.clone()).await?;
+        user::cleanup(obj, self.client.clone(), &self.recorder).await?;
+
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl Reconciler for Controller {
+    type Kind = crd::AuthentikUser;
+
+    const FINALIZER: &'static str = "authentik-user/ak.dany.dev";
+
+    fn instance(obj: &crd::AuthentikUser) -> Option<&str> {
+        Some(&obj.spec.authentik_instance)
+    }
+
+    async fn reconcile(&self, obj: Arc<crd::AuthentikUser>) -> Result<Action> {
         info!(
             "Starting reconcilidation of Authentik user `{}`.",
             obj.name_any()
         );
+        let ns = obj
+            .namespace()
+            .ok_or(anyhow!("Missing namespace `{}`.", obj.name_any()))?;
+        let api: Api<crd::AuthentikUser> = Api::namespaced(self.client.clone(), &ns);
+
+        // Reconcile all parts, and reflect the outcome in the status.
+        let mut status = obj.status.clone().unwrap_or_default();
+        status.observed_generation = obj.metadata.generation;
+        let result = self.reconcile_parts(&obj).await;
+
+        if let Err(e) = &result {
+            self.recorder
+                .warn(obj.as_ref(), "ReconcileFailed", "Reconcile", e.to_string())
+                .await;
+        } else {
+            self.recorder.resolved(obj.as_ref());
+        }
+
+        match &result {
+            Ok(pk) => status.succeeded(Some(pk.to_string())),
+            Err(e) => status.failed(e),
+        }
 
-        // Reconcile all parts.
-        user::reconcile(&obj, self.client.clone()).await?;
-        password::reconcile(&obj, self.client.clone()).await?;
-        group::reconcile(&obj, self.client.clone()).await?;
+        status::update(&api, obj.as_ref(), obj.status.as_ref(), &status, result).await?;
 
-        info!("Reconcilidation of Authentik user `{}` finished successfully, re-queued for 30 minutes.", obj.name_any());
-        Ok(Action::requeue(Duration::from_secs(30 * 60)))
+        let interval = requeue::resync_interval(obj.as_ref());
+        info!("Reconcilidation of Authentik user `{}` finished successfully, re-queued in {} seconds.", obj.name_any(), interval.as_secs());
+        Ok(Action::requeue(interval))
     }
 
-    pub async fn cleanup(&self, obj: Arc<crd::AuthentikUser>) -> Result<Action> {
-        group::cleanup(obj.as_ref(), self.client.clone()).await?;
-        password::cleanup(obj.as_ref(), self.client.clone()).await?;
-        user::cleanup(obj.as_ref(), self.client.clone()).await?;
+    async fn cleanup(&self, obj: Arc<crd::AuthentikUser>) -> Result<Action> {
+        if let Err(e) = self.cleanup_parts(obj.as_ref()).await {
+            self.recorder
+                .warn(obj.as_ref(), "CleanupFailed", "Cleanup", e.to_string())
+                .await;
+            return Err(e);
+        }
 
+        self.recorder.resolved(obj.as_ref());
         Ok(Action::await_change())
     }
 }
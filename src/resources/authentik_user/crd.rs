@@ -2,6 +2,8 @@ use kube::CustomResource;
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
+use crate::resources::status::ObjectStatus;
+
 #[derive(CustomResource, Deserialize, Serialize, Clone, Debug, JsonSchema)]
 #[serde(rename_all = "camelCase")]
 #[kube(
@@ -9,6 +11,10 @@ use serde::{Deserialize, Serialize};
     group = "ak.dany.dev",
     version = "v1",
     shortname = "akuser",
+    status = "ObjectStatus",
+    printcolumn = r#"{"name":"Instance", "type":"string", "jsonPath":".spec.authentikInstance"}"#,
+    printcolumn = r#"{"name":"Ready", "type":"string", "jsonPath":".status.conditions[?(@.type==\"Ready\")].status"}"#,
+    printcolumn = r#"{"name":"Age", "type":"date", "jsonPath":".metadata.creationTimestamp"}"#,
     namespaced
 )]
 pub struct AuthentikUserSpec {
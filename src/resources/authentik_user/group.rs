@@ -2,10 +2,10 @@ use anyhow::{anyhow, Result};
 use kube::{Client, ResourceExt};
 
 use crate::akapi::{
-    auth::get_valid_token,
+    auth::get_client,
     group::{FindGroup, FindGroupBody},
     user::{Find, FindBody, UpdateUser, UpdateUserBody},
-    AkApiRoute, AkClient,
+    AkApiRoute,
 };
 
 use super::crd;
@@ -16,9 +16,8 @@ pub async fn reconcile(obj: &crd::AuthentikUser, client: Client) -> Result<()> {
         .namespace()
         .ok_or(anyhow!("Missing namespace `{}`.", instance.clone()))?;
 
-    // Get the API key.
-    let api_key = get_valid_token(client.clone(), &ns, &instance).await?;
-    let ak = AkClient::new(&api_key, &instance, &ns)?;
+    // Get a client for the instance.
+    let ak = get_client(client.clone(), &ns, &instance).await?;
 
     // Find the ID's of the groups.
     let mut group_ids = Vec::new();
@@ -28,7 +27,6 @@ pub async fn reconcile(obj: &crd::AuthentikUser, client: Client) -> Result<()> {
             &ak,
             FindGroupBody {
                 name: Some(group_name.to_string()),
-                ..Default::default()
             },
         )
         .await?;
@@ -65,7 +63,6 @@ pub async fn reconcile(obj: &crd::AuthentikUser, client: Client) -> Result<()> {
         UpdateUserBody {
             id: user.pk,
             groups: Some(group_ids),
-            ..Default::default()
         },
     )
     .await?;
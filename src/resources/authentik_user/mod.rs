@@ -1,14 +1,6 @@
-use std::sync::Arc;
-
-use anyhow::anyhow;
 use futures::{future::BoxFuture, FutureExt, StreamExt};
 use k8s_openapi::api::core::v1::Secret;
-use kube::{
-    api::{Api, ListParams, ResourceExt},
-    runtime::{self, controller::Action, finalizer},
-    Client,
-};
-use tokio::{sync::Mutex, time::Duration};
+use kube::{api::ListParams, runtime, Client};
 
 mod controller;
 pub mod crd;
@@ -20,60 +12,40 @@ mod user;
 
 use controller::Controller;
 
-use crate::ReconcileError;
+use crate::metrics;
 
-use super::list_lp;
+use super::{error_policy, list_lp, reconcile, scoped_api, shard::Shard, Context};
 
 pub struct Manager;
 
 impl Manager {
-    pub fn new(client: Client) -> BoxFuture<'static, ()> {
-        let ctrlr = Controller::new(client.clone());
-        let users = Api::<crd::AuthentikUser>::all(client.clone());
+    #[allow(clippy::new_ret_no_self)]
+    pub fn new(client: Client, ns: Option<String>) -> BoxFuture<'static, ()> {
+        let (shard, shard_watch) = Shard::new(client.clone(), ns.as_deref());
+        let users = scoped_api::<crd::AuthentikUser>(client.clone(), ns.as_deref());
 
-        let secrets = Api::<Secret>::all(client.clone());
+        let secrets = scoped_api::<Secret>(client.clone(), ns.as_deref());
         let lp = list_lp("ak-user");
 
-        let drainer = runtime::Controller::new(users, ListParams::default())
+        let controller = runtime::Controller::new(users, ListParams::default());
+        metrics::count_objects(controller.store(), |obj| {
+            obj.spec.authentik_instance.clone()
+        });
+
+        let drainer = controller
             .owns(secrets, lp.clone())
             .run(
-                move |obj, controller| Self::reconcile(obj, controller, client.clone()),
-                move |_, e, _| Self::error_policy(e),
-                Arc::new(Mutex::new(ctrlr)),
+                reconcile::<Controller>,
+                error_policy::<Controller>,
+                Context::new(Controller::new(client.clone()), client, shard),
             )
             .filter_map(|x| async move { Result::ok(x) })
             .for_each(|_| futures::future::ready(()))
             .boxed();
 
-        drainer
-    }
-
-    async fn reconcile(
-        obj: Arc<crd::AuthentikUser>,
-        controller: Arc<Mutex<Controller>>,
-        client: Client,
-    ) -> Result<Action, ReconcileError> {
-        let ns = obj
-            .namespace()
-            .ok_or(anyhow!("Authentik user resource should have a namespace."))?;
-        let servers: Api<crd::AuthentikUser> = Api::namespaced(client, &ns);
-
-        finalizer(&servers, "authentik-user/ak.dany.dev", obj, |event| async {
-            // Make sure only one reconciliation can be run at the same time.
-            let controller = controller.lock().await;
-
-            match event {
-                finalizer::Event::Apply(server) => controller.reconcile(server).await,
-                finalizer::Event::Cleanup(server) => controller.cleanup(server).await,
-            }
-            .map_err(|e| e.into())
-        })
-        .await
-        .map_err(|e| e.into())
-    }
-
-    fn error_policy(error: &ReconcileError) -> Action {
-        warn!("{}", error);
-        Action::requeue(Duration::from_secs(60))
+        // The instances of the shard are watched for as long as the controller runs.
+        futures::future::select(drainer, shard_watch)
+            .map(|_| ())
+            .boxed()
     }
 }
@@ -1,27 +1,32 @@
 use anyhow::{anyhow, Result};
 use base64::encode;
 use k8s_openapi::api::core::v1::Secret;
-use kube::{api::PostParams, Api, Client, ResourceExt};
+use kube::{api::PostParams, runtime::events::EventType, Api, Client, ResourceExt};
 use rand::{distributions::Alphanumeric, thread_rng, Rng};
 use serde_json::json;
 
 use crate::akapi::{
-    auth::get_valid_token,
+    auth::get_client,
     user::{Find, FindBody, SetPassword, SetPasswordBody},
-    AkApiRoute, AkClient,
+    AkApiRoute,
 };
 
+use crate::resources::events::Recorder;
+
 use super::{crd, labels};
 
-pub async fn reconcile(obj: &crd::AuthentikUser, client: Client) -> Result<()> {
+pub async fn reconcile(
+    obj: &crd::AuthentikUser,
+    client: Client,
+    recorder: &Recorder,
+) -> Result<()> {
     let instance = obj.spec.authentik_instance.to_string();
     let ns = obj
         .namespace()
         .ok_or(anyhow!("Missing namespace `{}`.", instance.clone()))?;
 
-    // Get the API key.
-    let api_key = get_valid_token(client.clone(), &ns, &instance).await?;
-    let ak = AkClient::new(&api_key, &instance, &ns)?;
+    // Get a client for the instance.
+    let ak = get_client(client.clone(), &ns, &instance).await?;
 
     // Check if the secret already exists.
     let secret_api: Api<Secret> = Api::namespaced(client.clone(), &ns);
@@ -79,6 +84,15 @@ pub async fn reconcile(obj: &crd::AuthentikUser, client: Client) -> Result<()> {
         .await?;
 
     info!("Set the password for the user `{}`.", obj.name_any());
+    recorder
+        .publish(
+            obj,
+            EventType::Normal,
+            "Updated",
+            "SetPassword",
+            format!("Set the password of user `{}`.", obj.spec.username),
+        )
+        .await;
 
     Ok(())
 }
@@ -1,23 +1,28 @@
 use anyhow::{anyhow, Result};
-use kube::{Client, ResourceExt};
+use kube::{runtime::events::EventType, Client, ResourceExt};
 
 use crate::akapi::{
-    auth::get_valid_token,
+    auth::get_client,
     user::{CreateAccount, CreateAccountBody, DeleteAccount, DeleteAccountError, Find, FindBody},
-    AkApiRoute, AkClient,
+    AkApiRoute,
 };
 
+use crate::resources::events::Recorder;
+
 use super::crd;
 
-pub async fn reconcile(obj: &crd::AuthentikUser, client: Client) -> Result<()> {
+pub async fn reconcile(
+    obj: &crd::AuthentikUser,
+    client: Client,
+    recorder: &Recorder,
+) -> Result<usize> {
     let instance = obj.spec.authentik_instance.to_string();
     let ns = obj
         .namespace()
         .ok_or(anyhow!("Missing namespace `{}`.", instance.clone()))?;
 
-    // Get the API key.
-    let api_key = get_valid_token(client.clone(), &ns, &instance).await?;
-    let ak = AkClient::new(&api_key, &instance, &ns)?;
+    // Get a client for the instance.
+    let ak = get_client(client.clone(), &ns, &instance).await?;
 
     // Check if the account already exists.
     let result = Find::send(
@@ -29,16 +34,15 @@ pub async fn reconcile(obj: &crd::AuthentikUser, client: Client) -> Result<()> {
     )
     .await?;
 
-    match result
+    if let Some(user) = result
         .iter()
         .find(|&user| user.username == obj.spec.username)
     {
-        Some(_user) => return Ok(()),
-        None => (),
-    };
+        return Ok(user.pk);
+    }
 
     // Create the account as it does not exists.
-    CreateAccount::send(
+    let user = CreateAccount::send(
         &ak,
         CreateAccountBody {
             name: obj.spec.display_name.clone(),
@@ -49,19 +53,27 @@ pub async fn reconcile(obj: &crd::AuthentikUser, client: Client) -> Result<()> {
         },
     )
     .await?;
+    recorder
+        .publish(
+            obj,
+            EventType::Normal,
+            "Created",
+            "Create",
+            format!("Created user `{}`.", obj.spec.username),
+        )
+        .await;
 
-    Ok(())
+    Ok(user.pk)
 }
 
-pub async fn cleanup(obj: &crd::AuthentikUser, client: Client) -> Result<()> {
+pub async fn cleanup(obj: &crd::AuthentikUser, client: Client, recorder: &Recorder) -> Result<()> {
     let instance = obj.spec.authentik_instance.to_string();
     let ns = obj
         .namespace()
         .ok_or(anyhow!("Missing namespace `{}`.", instance.clone()))?;
 
-    // Get the API key.
-    let api_key = get_valid_token(client.clone(), &ns, &instance).await?;
-    let ak = AkClient::new(&api_key, &instance, &ns)?;
+    // Get a client for the instance.
+    let ak = get_client(client.clone(), &ns, &instance).await?;
 
     let result = Find::send(
         &ak,
@@ -83,6 +95,15 @@ pub async fn cleanup(obj: &crd::AuthentikUser, client: Client) -> Result<()> {
     match DeleteAccount::send(&ak, user.pk).await {
         Ok(_) => {
             info!("Deleted user {}.", obj.spec.username);
+            recorder
+                .publish(
+                    obj,
+                    EventType::Normal,
+                    "Deleted",
+                    "Delete",
+                    format!("Deleted user `{}`.", obj.spec.username),
+                )
+                .await;
             Ok(())
         }
         Err(DeleteAccountError::NotFound) => Ok(()),
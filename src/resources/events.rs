@@ -0,0 +1,103 @@
+use std::{collections::HashMap, env, sync::Mutex};
+
+use kube::{
+    runtime::{
+        events::{Event, EventType, Recorder as ObjectRecorder, Reporter},
+        reflector::ObjectRef,
+    },
+    Client, Resource,
+};
+
+/// Kubernetes rejects events with a note longer than this.
+const MAX_NOTE_LENGTH: usize = 1024;
+
+/// Publishes the events of a controller, it's created once per manager.
+/// The pod name is used as reporting instance when it's available.
+pub struct Recorder {
+    client: Client,
+    reporter: Reporter,
+    // The last warning per object, so a failing object doesn't publish the same one on every retry.
+    warnings: Mutex<HashMap<String, (String, String)>>,
+}
+
+impl Recorder {
+    pub fn new(client: Client) -> Self {
+        Self {
+            client,
+            reporter: Reporter {
+                controller: "authentik-operator".to_string(),
+                instance: env::var("POD_NAME").ok(),
+            },
+            warnings: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Publish an event regarding the given object.
+    /// Events are informational only, failing to publish one is logged but never fails a reconcile.
+    pub async fn publish<K>(
+        &self,
+        obj: &K,
+        type_: EventType,
+        reason: &str,
+        action: &str,
+        note: String,
+    ) where
+        K: Resource<DynamicType = ()>,
+    {
+        let mut note = note;
+        if note.len() > MAX_NOTE_LENGTH {
+            let mut end = MAX_NOTE_LENGTH;
+            while !note.is_char_boundary(end) {
+                end -= 1;
+            }
+            note.truncate(end);
+        }
+
+        let event = Event {
+            type_,
+            reason: reason.to_string(),
+            note: Some(note),
+            action: action.to_string(),
+            secondary: None,
+        };
+
+        let recorder = ObjectRecorder::new(
+            self.client.clone(),
+            self.reporter.clone(),
+            obj.object_ref(&()),
+        );
+        if let Err(e) = recorder.publish(event).await {
+            warn!("Failed to publish event `{}`: {}", reason, e);
+        }
+    }
+
+    /// Publish a warning regarding the given object, unless it's the same as the previous one.
+    pub async fn warn<K>(&self, obj: &K, reason: &str, action: &str, note: String)
+    where
+        K: Resource<DynamicType = ()>,
+    {
+        let warning = (reason.to_string(), note.clone());
+        let previous = self
+            .warnings
+            .lock()
+            .expect("Event warnings are poisoned")
+            .insert(ObjectRef::from_obj(obj).to_string(), warning.clone());
+        if previous.as_ref() == Some(&warning) {
+            return;
+        }
+
+        self.publish(obj, EventType::Warning, reason, action, note)
+            .await;
+    }
+
+    /// Forget the last warning of the object, once it reconciled successfully or is deleted.
+    pub fn resolved<K>(&self, obj: &K)
+    where
+        K: Resource<DynamicType = ()>,
+    {
+        self.warnings
+            .lock()
+            .expect("Event warnings are poisoned")
+            .remove(&ObjectRef::from_obj(obj).to_string());
+    }
+}
@@ -0,0 +1,77 @@
+use std::collections::BTreeMap;
+
+use anyhow::{anyhow, Result};
+use base64::encode;
+use k8s_openapi::api::core::v1::Secret;
+use kube::{
+    api::{Patch, PatchParams},
+    Api, Client, Resource, ResourceExt,
+};
+use rand::{distributions::Alphanumeric, thread_rng, Rng};
+use serde_json::json;
+
+/// Make sure a secret with generated values exists, owned by the given object.
+/// Values that are already present are kept as is, only missing keys are generated.
+/// Returns all values within the secret.
+pub async fn ensure_secret<K>(
+    client: Client,
+    owner: &K,
+    name: &str,
+    labels: BTreeMap<String, String>,
+    keys: &[(&str, usize)],
+) -> Result<BTreeMap<String, String>>
+where
+    K: Resource<DynamicType = ()>,
+{
+    let ns = owner
+        .namespace()
+        .ok_or(anyhow!("Missing namespace `{}`.", owner.name_any()))?;
+    let api: Api<Secret> = Api::namespaced(client, &ns);
+
+    let mut values = BTreeMap::new();
+    if let Some(secret) = api.get_opt(name).await? {
+        for (key, value) in secret.data.unwrap_or_default() {
+            values.insert(key, String::from_utf8(value.0)?);
+        }
+    }
+
+    let mut changed = false;
+    for (key, length) in keys {
+        if !values.contains_key(*key) {
+            values.insert(key.to_string(), random_string(*length));
+            changed = true;
+        }
+    }
+
+    if !changed {
+        return Ok(values);
+    }
+
+    let data: BTreeMap<&String, String> = values.iter().map(|(k, v)| (k, encode(v))).collect();
+    api.patch(
+        name,
+        &PatchParams::apply("authentik.ak-operator").force(),
+        &Patch::Apply(json!({
+            "apiVersion": "v1",
+            "kind": "Secret",
+            "type": "Opaque",
+            "metadata": {
+                "name": name,
+                "labels": labels,
+                "ownerReferences": [owner.controller_owner_ref(&())]
+            },
+            "data": data
+        })),
+    )
+    .await?;
+
+    Ok(values)
+}
+
+pub fn random_string(length: usize) -> String {
+    thread_rng()
+        .sample_iter(&Alphanumeric)
+        .take(length)
+        .map(char::from)
+        .collect()
+}
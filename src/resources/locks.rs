@@ -0,0 +1,32 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+
+use lazy_static::lazy_static;
+use tokio::sync::{OwnedRwLockReadGuard, OwnedRwLockWriteGuard, RwLock};
+
+lazy_static! {
+    static ref INSTANCES: Mutex<HashMap<String, Arc<RwLock<()>>>> = Mutex::new(HashMap::new());
+}
+
+fn instance_lock(ns: &str, instance: &str) -> Arc<RwLock<()>> {
+    INSTANCES
+        .lock()
+        .expect("Instance locks are poisoned")
+        .entry(format!("{}/{}", ns, instance))
+        .or_default()
+        .clone()
+}
+
+/// Hold the instance for a reconcile which uses its API.
+/// Any number of these can be held at the same time.
+pub async fn shared(ns: &str, instance: &str) -> OwnedRwLockReadGuard<()> {
+    instance_lock(ns, instance).read_owned().await
+}
+
+/// Hold the instance exclusively, waiting for all reconciles using its API to finish.
+/// Used when changing something the other reconciles depend on, like the operator token.
+pub async fn exclusive(ns: &str, instance: &str) -> OwnedRwLockWriteGuard<()> {
+    instance_lock(ns, instance).write_owned().await
+}
@@ -1,10 +1,38 @@
-use kube::api::ListParams;
+use std::{
+    collections::HashMap,
+    fmt::Debug,
+    sync::{Arc, Mutex},
+    time::Instant,
+};
+
+use anyhow::anyhow;
+use async_trait::async_trait;
+use kube::{
+    api::ListParams,
+    runtime::{controller::Action, finalizer, reflector::ObjectRef},
+    Api, Client, Resource, ResourceExt,
+};
+use lazy_static::lazy_static;
+use serde::{de::DeserializeOwned, Serialize};
+use tokio::sync::Semaphore;
+
+use crate::{config::CONFIG, metrics, ReconcileError};
+
+use requeue::Backoff;
+use shard::Shard;
 
 pub mod authentik;
 pub mod authentik_application;
+pub mod authentik_connection;
 pub mod authentik_group;
 pub mod authentik_provider_oauth;
 pub mod authentik_user;
+pub mod events;
+pub mod generated;
+pub mod locks;
+pub mod requeue;
+pub mod shard;
+pub mod status;
 
 pub use authentik::Manager as AuthentikManager;
 pub use authentik_application::Manager as AuthentikAppManager;
@@ -12,8 +40,148 @@ pub use authentik_group::Manager as AuthentikGroupManager;
 pub use authentik_provider_oauth::Manager as AuthentikOAuthManager;
 pub use authentik_user::Manager as AuthentikUserManager;
 
+lazy_static! {
+    // Limits of concurrent reconciles per kind, shared by the controllers of all watched namespaces.
+    static ref RECONCILE_LIMITS: Mutex<HashMap<String, Arc<Semaphore>>> = Mutex::new(HashMap::new());
+}
+
+/// The limit of objects of the kind which are reconciled at the same time.
+fn reconcile_limit<K: Resource<DynamicType = ()>>() -> Arc<Semaphore> {
+    RECONCILE_LIMITS
+        .lock()
+        .expect("Reconcile limits are poisoned")
+        .entry(K::kind(&()).to_string())
+        .or_insert_with(|| Arc::new(Semaphore::new(CONFIG.max_concurrent_reconciles)))
+        .clone()
+}
+
 fn list_lp(part_of: &str) -> ListParams {
     ListParams::default().labels(
         &format!("app.kubernetes.io/created-by=authentik-operator,app.kubernetes.io/name=authentik,app.kubernetes.io/part-of={}", part_of),
     )
 }
+
+/// Api for the kind within the watched namespace, or all namespaces if it's not set.
+fn scoped_api<K: Resource<Scope = k8s_openapi::NamespaceResourceScope>>(
+    client: Client,
+    ns: Option<&str>,
+) -> Api<K>
+where
+    K::DynamicType: Default,
+{
+    match ns {
+        Some(ns) => Api::namespaced(client, ns),
+        None => Api::all(client),
+    }
+}
+
+/// Reconciles the objects of a kind, called through its finalizer.
+#[async_trait]
+pub trait Reconciler: Send + Sync + 'static {
+    type Kind: Resource<DynamicType = (), Scope = k8s_openapi::NamespaceResourceScope>
+        + Clone
+        + DeserializeOwned
+        + Serialize
+        + Debug
+        + Send
+        + Sync
+        + 'static;
+
+    /// Finalizer which keeps the objects around until they're cleaned up.
+    const FINALIZER: &'static str;
+
+    /// The instance the object belongs to, if it uses the API of one.
+    fn instance(_obj: &Self::Kind) -> Option<&str> {
+        None
+    }
+
+    async fn reconcile(&self, obj: Arc<Self::Kind>) -> anyhow::Result<Action>;
+
+    async fn cleanup(&self, obj: Arc<Self::Kind>) -> anyhow::Result<Action>;
+}
+
+/// State shared by all reconciles of a controller.
+pub struct Context<C: Reconciler> {
+    controller: C,
+    client: Client,
+    backoff: Backoff<C::Kind>,
+    limit: Arc<Semaphore>,
+    shard: Shard,
+}
+
+impl<C: Reconciler> Context<C> {
+    pub fn new(controller: C, client: Client, shard: Shard) -> Arc<Self> {
+        Arc::new(Self {
+            controller,
+            client,
+            backoff: Backoff::default(),
+            limit: reconcile_limit::<C::Kind>(),
+            shard,
+        })
+    }
+}
+
+/// Reconcile an object, within the limits of its kind and the shard of this operator.
+pub async fn reconcile<C: Reconciler>(
+    obj: Arc<C::Kind>,
+    ctx: Arc<Context<C>>,
+) -> Result<Action, ReconcileError> {
+    let obj_ref = ObjectRef::from_obj(obj.as_ref());
+    let ns = obj.namespace().ok_or(anyhow!(
+        "{} resource should have a namespace.",
+        C::Kind::kind(&())
+    ))?;
+    let api: Api<C::Kind> = Api::namespaced(ctx.client.clone(), &ns);
+
+    let instance = C::instance(&obj).map(|instance| instance.to_string());
+    if let Some(instance) = &instance {
+        // Resources of instances within another shard are left to the operator of that shard.
+        if !ctx.shard.contains_instance(&ns, instance).await? {
+            return Ok(Action::requeue(requeue::resync_interval(obj.as_ref())));
+        }
+    }
+
+    // kube-runtime never reconciles the same object twice at the same time, only the total is limited.
+    let _permit = ctx
+        .limit
+        .clone()
+        .acquire_owned()
+        .await
+        .map_err(|e| anyhow!(e))?;
+    // Rotating the token of the instance waits until the reconciles using it are done.
+    let _instance = match &instance {
+        Some(instance) => Some(locks::shared(&ns, instance).await),
+        None => None,
+    };
+
+    let started = Instant::now();
+    let result = finalizer(&api, C::FINALIZER, obj, |event| async {
+        match event {
+            finalizer::Event::Apply(obj) => ctx.controller.reconcile(obj).await,
+            finalizer::Event::Cleanup(obj) => ctx.controller.cleanup(obj).await,
+        }
+        .map_err(|e| e.into())
+    })
+    .await
+    .map_err(|e| e.into());
+
+    metrics::reconciled::<C::Kind>(started, result.is_ok());
+
+    // Start over with the shortest delay once the object reconciles again.
+    if result.is_ok() {
+        ctx.backoff.reset(&obj_ref);
+    }
+
+    result
+}
+
+/// Retry a failed reconcile after a delay which grows with each failure.
+pub fn error_policy<C: Reconciler>(
+    obj: Arc<C::Kind>,
+    error: &ReconcileError,
+    ctx: Arc<Context<C>>,
+) -> Action {
+    let delay = ctx.backoff.failed(obj.as_ref());
+    warn!("{}, retrying in {} seconds.", error, delay.as_secs());
+    Action::requeue(delay)
+}
@@ -0,0 +1,75 @@
+use std::{
+    collections::HashMap,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use kube::{runtime::reflector::ObjectRef, Resource, ResourceExt};
+
+use crate::config::{parse_duration, CONFIG};
+
+/// Annotation to override the resync interval of a single object.
+pub static RESYNC_ANNOTATION: &str = "ak.dany.dev/resync-interval";
+
+/// The interval after which the object should be reconciled again.
+/// Taken from the annotation on the object if it's set, the global interval otherwise.
+pub fn resync_interval<K: Resource>(obj: &K) -> Duration {
+    match obj.annotations().get(RESYNC_ANNOTATION) {
+        Some(value) => parse_duration(value).unwrap_or_else(|| {
+            warn!(
+                "Invalid `{}` annotation `{}` on `{}`, using the default.",
+                RESYNC_ANNOTATION,
+                value,
+                obj.name_any()
+            );
+            CONFIG.resync_interval
+        }),
+        None => CONFIG.resync_interval,
+    }
+}
+
+/// Keeps track of the consecutive failures of objects, to retry them with an exponential backoff.
+/// Failures are forgotten once the object reconciles, or when it hasn't failed for a while.
+pub struct Backoff<K: Resource<DynamicType = ()>> {
+    failures: Mutex<HashMap<ObjectRef<K>, (u32, Instant)>>,
+}
+
+impl<K: Resource<DynamicType = ()>> Default for Backoff<K> {
+    fn default() -> Self {
+        Self {
+            failures: Mutex::new(HashMap::new()),
+        }
+    }
+}
+
+impl<K: Resource<DynamicType = ()>> Backoff<K> {
+    /// Forget the failures of the object, after it was reconciled successfully.
+    pub fn reset(&self, obj: &ObjectRef<K>) {
+        self.failures
+            .lock()
+            .expect("Backoff lock poisoned")
+            .remove(obj);
+    }
+
+    /// Register a failure of the object, and get the delay before it should be retried.
+    pub fn failed(&self, obj: &K) -> Duration {
+        let mut failures = self.failures.lock().expect("Backoff lock poisoned");
+
+        // A failing object is retried within the maximum delay, so older entries belong to
+        // objects which were deleted in the meantime.
+        let expiry = CONFIG.backoff_max.saturating_mul(2);
+        failures.retain(|_, (_, last)| last.elapsed() <= expiry);
+
+        let (count, last) = failures
+            .entry(ObjectRef::from_obj(obj))
+            .or_insert((0, Instant::now()));
+        let delay = CONFIG
+            .backoff_min
+            .saturating_mul(2u32.saturating_pow(*count))
+            .min(CONFIG.backoff_max);
+        *count = count.saturating_add(1);
+        *last = Instant::now();
+
+        delay
+    }
+}
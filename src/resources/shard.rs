@@ -0,0 +1,160 @@
+use std::{
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use anyhow::{anyhow, Result};
+use futures::{future::BoxFuture, FutureExt, StreamExt};
+use kube::{
+    api::ListParams,
+    runtime::{
+        reflector::{self, store::Writer, ObjectRef, Store},
+        watcher,
+    },
+    Api, Client, Resource,
+};
+use serde::de::DeserializeOwned;
+use tokio::sync::Notify;
+
+use crate::config::CONFIG;
+
+use super::{
+    authentik::crd::Authentik, authentik_connection::crd::AuthentikConnection, scoped_api,
+};
+
+/// How long reconciles wait for the instances of the shard to be listed, before failing.
+const LIST_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// List params for the instances within the shard of this operator.
+pub fn list_params() -> ListParams {
+    match &CONFIG.shard_selector {
+        Some(selector) => ListParams::default().labels(selector),
+        None => ListParams::default(),
+    }
+}
+
+/// Local copies of the instances and connections within the shard of this operator.
+/// The default shard contains all instances, like when the operator isn't sharded.
+#[derive(Clone, Default)]
+pub struct Shard {
+    // None when the operator isn't sharded.
+    stores: Option<Arc<Stores>>,
+}
+
+struct Stores {
+    servers: Store<Authentik>,
+    connections: Store<AuthentikConnection>,
+    // Stores which haven't been listed yet.
+    pending: AtomicUsize,
+    listed: Notify,
+}
+
+impl Shard {
+    /// Watch the instances and connections of the shard within the namespace, or all namespaces.
+    /// The returned future keeps the stores up to date, it has to run alongside the controller.
+    pub fn new(client: Client, ns: Option<&str>) -> (Self, BoxFuture<'static, ()>) {
+        if CONFIG.shard_selector.is_none() {
+            return (Self { stores: None }, futures::future::pending().boxed());
+        }
+
+        let (servers, server_writer) = reflector::store();
+        let (connections, connection_writer) = reflector::store();
+        let stores = Arc::new(Stores {
+            servers,
+            connections,
+            pending: AtomicUsize::new(2),
+            listed: Notify::new(),
+        });
+
+        let watch = futures::future::join(
+            reflect(
+                stores.clone(),
+                server_writer,
+                scoped_api(client.clone(), ns),
+            ),
+            reflect(stores.clone(), connection_writer, scoped_api(client, ns)),
+        );
+
+        (
+            Self {
+                stores: Some(stores),
+            },
+            watch.map(|_| ()).boxed(),
+        )
+    }
+
+    /// Check if the instance or connection referenced by a resource is within the shard of this operator.
+    /// Always true when the operator isn't sharded, otherwise waits until the stores are listed.
+    pub async fn contains_instance(&self, ns: &str, instance: &str) -> Result<bool> {
+        let stores = match &self.stores {
+            Some(stores) => stores,
+            None => return Ok(true),
+        };
+
+        // A watch which keeps failing would hold up the reconcile forever, fail it instead so it's retried.
+        tokio::time::timeout(LIST_TIMEOUT, stores.wait_listed())
+            .await
+            .map_err(|_| {
+                anyhow!(
+                    "The instances of the shard weren't listed within {} seconds.",
+                    LIST_TIMEOUT.as_secs()
+                )
+            })?;
+
+        Ok(stores
+            .servers
+            .get(&ObjectRef::new(instance).within(ns))
+            .is_some()
+            || stores
+                .connections
+                .get(&ObjectRef::new(instance).within(ns))
+                .is_some())
+    }
+}
+
+impl Stores {
+    /// Wait until all stores are listed.
+    async fn wait_listed(&self) {
+        while self.pending.load(Ordering::SeqCst) > 0 {
+            // Created before checking again, so the notification can't be missed.
+            let listed = self.listed.notified();
+            if self.pending.load(Ordering::SeqCst) == 0 {
+                break;
+            }
+            listed.await;
+        }
+    }
+}
+
+/// Keep the store up to date with the objects of the shard, and mark it as listed after the first list.
+fn reflect<K>(stores: Arc<Stores>, writer: Writer<K>, api: Api<K>) -> BoxFuture<'static, ()>
+where
+    K: Resource<DynamicType = ()>
+        + Clone
+        + DeserializeOwned
+        + std::fmt::Debug
+        + Send
+        + Sync
+        + 'static,
+{
+    let mut listed = false;
+
+    reflector::reflector(writer, watcher(api, list_params()))
+        .for_each(move |event| {
+            match event {
+                Ok(watcher::Event::Restarted(_)) if !listed => {
+                    listed = true;
+                    if stores.pending.fetch_sub(1, Ordering::SeqCst) == 1 {
+                        stores.listed.notify_waiters();
+                    }
+                }
+                Ok(_) => {}
+                Err(e) => warn!("Failed to watch the {} of the shard: {}", K::plural(&()), e),
+            }
+            futures::future::ready(())
+        })
+        .boxed()
+}
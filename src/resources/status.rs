@@ -0,0 +1,156 @@
+use std::fmt::Debug;
+
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use kube::{
+    api::{Patch, PatchParams},
+    Api, Resource, ResourceExt,
+};
+use schemars::JsonSchema;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::json;
+
+pub static READY: &str = "Ready";
+
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct Condition {
+    #[serde(rename = "type")]
+    pub type_: String,
+    pub status: String,
+    pub reason: String,
+    #[serde(default)]
+    pub message: String,
+    pub last_transition_time: DateTime<Utc>,
+    pub observed_generation: Option<i64>,
+}
+
+/// Status shared by all resources which manage a single object within Authentik.
+#[derive(Deserialize, Serialize, Clone, Debug, Default, PartialEq, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ObjectStatus {
+    #[serde(default)]
+    pub conditions: Vec<Condition>,
+    pub observed_generation: Option<i64>,
+    pub pk: Option<String>,
+    pub last_error: Option<String>,
+}
+
+impl ObjectStatus {
+    pub fn succeeded(&mut self, pk: Option<String>) {
+        if pk.is_some() {
+            self.pk = pk;
+        }
+        self.last_error = None;
+
+        set_condition(
+            &mut self.conditions,
+            READY,
+            true,
+            "ReconcileSucceeded",
+            "",
+            self.observed_generation,
+        );
+    }
+
+    pub fn failed(&mut self, error: &anyhow::Error) {
+        let message = error.to_string();
+        set_condition(
+            &mut self.conditions,
+            READY,
+            false,
+            "ReconcileFailed",
+            &message,
+            self.observed_generation,
+        );
+
+        self.last_error = Some(message);
+    }
+}
+
+pub fn set_condition(
+    conditions: &mut Vec<Condition>,
+    type_: &str,
+    status: bool,
+    reason: &str,
+    message: &str,
+    generation: Option<i64>,
+) {
+    let status = if status { "True" } else { "False" }.to_string();
+
+    match conditions.iter_mut().find(|c| c.type_ == type_) {
+        Some(condition) => {
+            // Only move the transition time if the status actually flipped.
+            if condition.status != status {
+                condition.last_transition_time = Utc::now();
+            }
+
+            condition.status = status;
+            condition.reason = reason.to_string();
+            condition.message = message.to_string();
+            condition.observed_generation = generation;
+        }
+        None => conditions.push(Condition {
+            type_: type_.to_string(),
+            status,
+            reason: reason.to_string(),
+            message: message.to_string(),
+            last_transition_time: Utc::now(),
+            observed_generation: generation,
+        }),
+    }
+}
+
+pub fn is_condition_true(conditions: &[Condition], type_: &str) -> bool {
+    conditions
+        .iter()
+        .any(|c| c.type_ == type_ && c.status == "True")
+}
+
+pub async fn patch_status<K, S>(api: &Api<K>, name: &str, status: &S) -> Result<()>
+where
+    K: Resource<DynamicType = ()> + Clone + DeserializeOwned + Debug,
+    S: Serialize,
+{
+    api.patch_status(
+        name,
+        &PatchParams::apply("authentik.ak-operator").force(),
+        &Patch::Apply(json!({
+            "apiVersion": K::api_version(&()),
+            "kind": K::kind(&()),
+            "status": status
+        })),
+    )
+    .await?;
+
+    Ok(())
+}
+
+/// Update the status if it changed, passing on the result of the reconcile.
+/// The reconcile error is reported rather than a failed status update.
+pub async fn update<K, S, T>(
+    api: &Api<K>,
+    obj: &K,
+    current: Option<&S>,
+    status: &S,
+    result: Result<T>,
+) -> Result<T>
+where
+    K: Resource<DynamicType = ()> + Clone + DeserializeOwned + Debug,
+    S: Serialize + PartialEq,
+{
+    if current != Some(status) {
+        if let Err(e) = patch_status(api, &obj.name_any(), status).await {
+            warn!(
+                "Failed to update the status of {} `{}`: {}",
+                K::kind(&()),
+                obj.name_any(),
+                e
+            );
+            result?;
+            return Err(e);
+        }
+    }
+
+    result
+}